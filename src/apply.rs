@@ -0,0 +1,340 @@
+use crate::language::{Eval, Expr};
+use crate::sygus::{Definition, Sort};
+use crate::table::{self, Table};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A synthesized program, ready to run on rows of text cells
+#[derive(Clone, Debug)]
+pub struct Program {
+    pub parameters: Vec<(String, Sort)>,
+    pub body: Expr,
+}
+
+impl Program {
+    pub fn new(parameters: Vec<(String, Sort)>, body: Expr) -> Program {
+        Program { parameters, body }
+    }
+
+    /// The program of a `define-fun` command, such as one printed by a
+    /// previous run
+    pub fn from_definition(definition: &Definition) -> Result<Program, String> {
+        let body = (&definition.body)
+            .try_into()
+            .map_err(|_| format!("unsupported body: {}", definition.body))?;
+        Ok(Program::new(definition.parameters.clone(), body))
+    }
+
+    /// The output on one row, with a cell per parameter, written as text
+    pub fn run(&self, cells: &[&str]) -> Result<String, String> {
+        if cells.len() != self.parameters.len() {
+            return Err(format!(
+                "expected {} inputs, found {}",
+                self.parameters.len(),
+                cells.len()
+            ));
+        }
+        let mut env = HashMap::new();
+        for ((name, sort), cell) in self.parameters.iter().zip(cells) {
+            let value = match sort.to_string().as_str() {
+                "String" => Expr::ConstStr(cell.to_string()),
+                "Int" => Expr::ConstInt(cell.parse().map_err(|_| format!("not an Int: {}", cell))?),
+                "Bool" => {
+                    Expr::ConstBool(cell.parse().map_err(|_| format!("not a Bool: {}", cell))?)
+                }
+                sort => return Err(format!("unsupported parameter sort: {}", sort)),
+            };
+            env.insert(name.clone(), value);
+        }
+        match self.body.clone().eval(&env)? {
+            Expr::ConstStr(s) => Ok(s),
+            Expr::ConstInt(n) => Ok(n.to_string()),
+            Expr::ConstBool(b) => Ok(b.to_string()),
+            e => Err(format!("not a value: {:?}", e)),
+        }
+    }
+}
+
+/// How rows of inputs are laid out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// One input per line, for programs of one parameter
+    Lines,
+    /// A table with a header row, and a column per parameter
+    Table(char),
+    /// One JSON object per line, with a field per parameter
+    JsonLines,
+}
+
+impl Format {
+    /// The format of a file, by its extension
+    pub fn of_path(path: &str) -> Format {
+        match path.rsplit_once('.').map(|(_, extension)| extension) {
+            Some("csv") => Format::Table(','),
+            Some("tsv") => Format::Table('\t'),
+            Some("jsonl") | Some("ndjson") => Format::JsonLines,
+            _ => Format::Lines,
+        }
+    }
+}
+
+/// Runs `program` on every row of `input`, writing a row of `output` for
+/// each: lines get the output, or an empty line and the error on standard
+/// error; tables get `output` and `error` columns, renamed if the input has
+/// columns of those names already; JSON lines get an object
+/// with an `output` or an `error` field. Returns the number of rows and of
+/// errors. Rows are streamed, so inputs of any length fit in memory.
+pub fn run(
+    program: &Program,
+    format: Format,
+    mut input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<(usize, usize)> {
+    let (mut rows, mut errors) = (0, 0);
+    match format {
+        Format::Lines => {
+            for line in input.lines() {
+                rows += 1;
+                match program.run(&[&line?]) {
+                    Ok(result) => writeln!(output, "{}", result)?,
+                    Err(e) => {
+                        errors += 1;
+                        eprintln!("row {}: {}", rows, e);
+                        writeln!(output)?;
+                    }
+                }
+            }
+        }
+        Format::JsonLines => {
+            for line in input.lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                rows += 1;
+                let result = parse_object(&line).and_then(|fields| {
+                    let cells: Result<Vec<&str>, String> = program
+                        .parameters
+                        .iter()
+                        .map(|(name, _)| {
+                            let cell = fields.get(name).ok_or(format!("no field {}", name))?;
+                            Ok(cell.as_str())
+                        })
+                        .collect();
+                    program.run(&cells?)
+                });
+                match result {
+                    Ok(result) => writeln!(output, "{{\"output\": {}}}", json_string(&result))?,
+                    Err(e) => {
+                        errors += 1;
+                        writeln!(output, "{{\"error\": {}}}", json_string(&e))?
+                    }
+                }
+            }
+        }
+        Format::Table(delimiter) => {
+            let invalid = |e: &str| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+            let mut headers = table::read_record(&mut input, delimiter)?
+                .ok_or_else(|| invalid("no header row"))?;
+            // columns are matched by their header, or its parameter name
+            let table = Table {
+                headers: headers.clone(),
+                rows: Vec::new(),
+            };
+            let all: Vec<usize> = (0..headers.len()).collect();
+            let names = table.parameters(&all);
+            let columns: Option<Vec<usize>> = program
+                .parameters
+                .iter()
+                .map(|(name, _)| {
+                    table
+                        .column(name)
+                        .or_else(|| names.iter().position(|n| n == name))
+                })
+                .collect();
+            let columns = columns.ok_or_else(|| invalid("missing an input column"))?;
+            let width = headers.len();
+            for header in ["output", "error"] {
+                headers.push(table::fresh(&headers, header));
+            }
+            write!(output, "{}", table::write_record(&headers, delimiter))?;
+            while let Some(mut row) = table::read_record(&mut input, delimiter)? {
+                rows += 1;
+                if row.len() > width {
+                    return Err(invalid(&format!(
+                        "row {} has more cells than headers",
+                        rows
+                    )));
+                }
+                row.resize(width, String::new());
+                let cells: Vec<&str> = columns.iter().map(|&c| row[c].as_str()).collect();
+                let result = program.run(&cells);
+                errors += result.is_err() as usize;
+                let (result, error) = match result {
+                    Ok(result) => (result, String::new()),
+                    Err(e) => (String::new(), e),
+                };
+                row.extend([result, error]);
+                write!(output, "{}", table::write_record(&row, delimiter))?;
+            }
+        }
+    }
+    Ok((rows, errors))
+}
+
+/// The fields of a flat JSON object, with numbers and booleans as they are
+/// written and `null` as empty
+fn parse_object(line: &str) -> Result<HashMap<String, String>, String> {
+    let mut chars = line.trim().chars().peekable();
+    let mut fields = HashMap::new();
+    expect(&mut chars, '{')?;
+    skip_whitespace(&mut chars);
+    if chars.next_if_eq(&'}').is_some() {
+        return Ok(fields);
+    }
+    loop {
+        skip_whitespace(&mut chars);
+        let key = parse_string(&mut chars)?;
+        skip_whitespace(&mut chars);
+        expect(&mut chars, ':')?;
+        skip_whitespace(&mut chars);
+        let value = if chars.peek() == Some(&'"') {
+            parse_string(&mut chars)?
+        } else {
+            let mut value = String::new();
+            while let Some(c) = chars.next_if(|c| !matches!(c, ',' | '}') && !c.is_whitespace()) {
+                value.push(c);
+            }
+            match value.as_str() {
+                "null" => String::new(),
+                "true" | "false" => value,
+                _ if value.parse::<f64>().is_ok() => value,
+                _ => return Err(format!("unsupported JSON value: {}", value)),
+            }
+        };
+        fields.insert(key, value);
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            Some(',') => continue,
+            Some('}') if chars.next().is_none() => return Ok(fields),
+            _ => return Err("expected a flat JSON object".to_string()),
+        }
+    }
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    expect(chars, '"')?;
+    let mut s = String::new();
+    loop {
+        let c = match chars.next().ok_or("unterminated JSON string")? {
+            '"' => return Ok(s),
+            '\\' => match chars.next().ok_or("unterminated JSON string")? {
+                'b' => '\u{8}',
+                'f' => '\u{c}',
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                'u' => {
+                    let mut unit = code_unit(chars)?;
+                    // a high surrogate pairs with a low one escaped right after it
+                    if (0xD800..0xDC00).contains(&unit) {
+                        let low = match (chars.next(), chars.next()) {
+                            (Some('\\'), Some('u')) => code_unit(chars)?,
+                            _ => 0,
+                        };
+                        if !(0xDC00..0xE000).contains(&low) {
+                            return Err(format!("unpaired surrogate in JSON: \\u{:04x}", unit));
+                        }
+                        unit = 0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00);
+                    }
+                    char::from_u32(unit).ok_or(format!("bad JSON escape: \\u{:04x}", unit))?
+                }
+                c => c,
+            },
+            c => c,
+        };
+        s.push(c);
+    }
+}
+
+/// The UTF-16 code unit of a `\u` escape, from its four hex digits
+fn code_unit(chars: &mut Peekable<Chars>) -> Result<u32, String> {
+    let hex: String = chars.by_ref().take(4).collect();
+    if hex.len() != 4 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("bad JSON escape: \\u{}", hex));
+    }
+    Ok(u32::from_str_radix(&hex, 16).unwrap())
+}
+
+fn expect(chars: &mut Peekable<Chars>, c: char) -> Result<(), String> {
+    chars
+        .next_if_eq(&c)
+        .map(|_| ())
+        .ok_or_else(|| format!("expected {:?} in JSON", c))
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+/// `s` as a JSON string
+fn json_string(s: &str) -> String {
+    let mut json = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sygus;
+
+    #[test]
+    fn apply_in_batch() {
+        let file = "(define-fun f ((name String)) String \
+                    (str.substr name 0 (str.indexof name \" \" 0)))";
+        let definitions = sygus::parse_definitions(file).expect("definition");
+        let program = Program::from_definition(&definitions[0]).expect("program");
+
+        let mut output = Vec::new();
+        let input = "{\"name\": \"Jan Kotas\"}\n{\"name\": \"Bo\\u00e9 Li\", \"age\": 3}\n{}\n";
+        let counts = run(&program, Format::JsonLines, input.as_bytes(), &mut output).unwrap();
+        assert_eq!(counts, (3, 1));
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"output\": \"Jan\"}\n{\"output\": \"Boé\"}\n{\"error\": \"no field name\"}\n"
+        );
+
+        // surrogates pair up only with a low surrogate right after them
+        let mut output = Vec::new();
+        let input = "{\"name\": \"\\ud83d\\ude00 x\"}\n{\"name\": \"\\ud800\\u0041 x\"}\n\
+                     {\"name\": \"\\ud800 x\"}\n";
+        let counts = run(&program, Format::JsonLines, input.as_bytes(), &mut output).unwrap();
+        assert_eq!(counts, (3, 2));
+        assert!(String::from_utf8(output)
+            .unwrap()
+            .starts_with("{\"output\": \"\u{1f600}\"}\n"));
+
+        let mut output = Vec::new();
+        let input = "id,name,output\n1,\"Jan\nKotas\",\n2,Bo Li\n";
+        run(&program, Format::Table(','), input.as_bytes(), &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "id,name,output,output_2,error\n\
+             1,\"Jan\nKotas\",,,\n2,Bo Li,,Bo,\n"
+        );
+    }
+}
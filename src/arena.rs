@@ -0,0 +1,109 @@
+use crate::sygus::{Literal, Term};
+use std::collections::HashMap;
+
+/// Identifies a term in a [`TermArena`]. Equal ids mean equal terms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TermId(u32);
+
+impl TermId {
+    /// The position of the term in its arena, which counts up from 0
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// One node of a term, whose children are ids in the same arena. Names and
+/// literals are borrowed from the grammar the terms come from.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Node<'g> {
+    Identifier(&'g str),
+    Literal(&'g Literal),
+    Application(&'g str, Box<[TermId]>),
+}
+
+/// Hash-consed terms: each distinct term is stored once, and shares its
+/// subterms with every other term containing them.
+#[derive(Debug, Default)]
+pub struct TermArena<'g> {
+    nodes: Vec<Node<'g>>,
+    ids: HashMap<Node<'g>, TermId>,
+    /// The number of nodes in each term, by id
+    sizes: Vec<usize>,
+}
+
+impl<'g> TermArena<'g> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The id of `node`, and whether it is new to the arena
+    pub fn add(&mut self, node: Node<'g>) -> (TermId, bool) {
+        if let Some(id) = self.ids.get(&node) {
+            return (*id, false);
+        }
+        let id = TermId(u32::try_from(self.nodes.len()).expect("too many terms"));
+        let size = match &node {
+            Node::Application(_, args) => 1 + args.iter().map(|a| self.size(*a)).sum::<usize>(),
+            _ => 1,
+        };
+        self.sizes.push(size);
+        self.nodes.push(node.clone());
+        self.ids.insert(node, id);
+        (id, true)
+    }
+
+    /// The id of `node`, if it is in the arena
+    pub fn get(&self, node: &Node<'g>) -> Option<TermId> {
+        self.ids.get(node).copied()
+    }
+
+    pub fn node(&self, id: TermId) -> &Node<'g> {
+        &self.nodes[id.index()]
+    }
+
+    /// The number of nodes in term `id`, without rebuilding it
+    pub fn size(&self, id: TermId) -> usize {
+        self.sizes[id.index()]
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Rebuilds the term `id` as a tree
+    pub fn term(&self, id: TermId) -> Term {
+        match self.node(id) {
+            Node::Identifier(name) => Term::Identifier(name.to_string()),
+            Node::Literal(lit) => Term::Literal((*lit).clone()),
+            Node::Application(name, args) => Term::Application(
+                name.to_string(),
+                args.iter().map(|a| self.term(*a)).collect(),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn share_subterms() {
+        let zero = Literal::Numeral(0);
+        let mut arena = TermArena::new();
+        let (name, _) = arena.add(Node::Identifier("name"));
+        let (n, _) = arena.add(Node::Literal(&zero));
+        let at = || Node::Application("str.at", Box::new([name, n]));
+        let (a, fresh_a) = arena.add(at());
+        let (b, fresh_b) = arena.add(at());
+        assert!(fresh_a && !fresh_b);
+        assert_eq!(a, b);
+        assert_eq!(arena.len(), 3);
+        assert_eq!(arena.size(a), 3);
+        assert_eq!(arena.term(a).to_string(), "(str.at name 0)");
+    }
+}
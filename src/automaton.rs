@@ -0,0 +1,247 @@
+use std::fmt;
+
+/// The largest code point of the SMT-LIB string alphabet (Unicode planes 0-2).
+pub const MAX_CHAR: char = '\u{2FFFF}';
+
+/// A ground regular language over code points. Membership is decided with
+/// Brzozowski derivatives, which handle intersection and complement directly.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Re {
+    /// The empty language
+    Empty,
+    /// The language containing only the empty string
+    Epsilon,
+    /// Singleton strings of one character in an inclusive range
+    Range(char, char),
+    Concat(Box<Re>, Box<Re>),
+    Union(Box<Re>, Box<Re>),
+    Inter(Box<Re>, Box<Re>),
+    Star(Box<Re>),
+    Comp(Box<Re>),
+    /// Between the two counts of repetitions, kept counted rather than
+    /// expanded into copies
+    Loop(Box<Re>, u32, u32),
+}
+
+impl Re {
+    pub fn all_char() -> Re {
+        Re::Range('\0', MAX_CHAR)
+    }
+
+    pub fn all() -> Re {
+        Re::star(Re::all_char())
+    }
+
+    pub fn literal(s: &str) -> Re {
+        s.chars()
+            .map(|c| Re::Range(c, c))
+            .fold(Re::Epsilon, Re::concat)
+    }
+
+    pub fn range(lo: char, hi: char) -> Re {
+        if lo <= hi {
+            Re::Range(lo, hi)
+        } else {
+            Re::Empty
+        }
+    }
+
+    pub fn concat(a: Re, b: Re) -> Re {
+        match (a, b) {
+            (Re::Empty, _) | (_, Re::Empty) => Re::Empty,
+            (Re::Epsilon, r) | (r, Re::Epsilon) => r,
+            (a, b) => Re::Concat(Box::new(a), Box::new(b)),
+        }
+    }
+
+    pub fn union(a: Re, b: Re) -> Re {
+        match (a, b) {
+            (Re::Empty, r) | (r, Re::Empty) => r,
+            (Re::Comp(e), _) | (_, Re::Comp(e)) if *e == Re::Empty => Re::Comp(e),
+            (a, b) if a == b => a,
+            // keep unions ordered so that equal languages share a representation
+            (a, b) if a > b => Re::Union(Box::new(b), Box::new(a)),
+            (a, b) => Re::Union(Box::new(a), Box::new(b)),
+        }
+    }
+
+    pub fn inter(a: Re, b: Re) -> Re {
+        match (a, b) {
+            (Re::Empty, _) | (_, Re::Empty) => Re::Empty,
+            (Re::Comp(e), r) | (r, Re::Comp(e)) if *e == Re::Empty => r,
+            (a, b) if a == b => a,
+            (a, b) if a > b => Re::Inter(Box::new(b), Box::new(a)),
+            (a, b) => Re::Inter(Box::new(a), Box::new(b)),
+        }
+    }
+
+    pub fn star(r: Re) -> Re {
+        match r {
+            Re::Empty | Re::Epsilon => Re::Epsilon,
+            r @ Re::Star(_) => r,
+            r => Re::Star(Box::new(r)),
+        }
+    }
+
+    pub fn comp(r: Re) -> Re {
+        match r {
+            Re::Comp(r) => *r,
+            r => Re::Comp(Box::new(r)),
+        }
+    }
+
+    /// `r` repeated between `lo` and `hi` times
+    pub fn power(r: Re, lo: u32, hi: u32) -> Re {
+        match r {
+            _ if lo > hi => Re::Empty,
+            _ if hi == 0 => Re::Epsilon,
+            Re::Empty if lo == 0 => Re::Epsilon,
+            Re::Empty => Re::Empty,
+            Re::Epsilon => Re::Epsilon,
+            r if (lo, hi) == (1, 1) => r,
+            r => Re::Loop(Box::new(r), lo, hi),
+        }
+    }
+
+    /// Whether the language contains the empty string
+    pub fn nullable(&self) -> bool {
+        match self {
+            Re::Empty | Re::Range(_, _) => false,
+            Re::Epsilon | Re::Star(_) => true,
+            Re::Concat(a, b) | Re::Inter(a, b) => a.nullable() && b.nullable(),
+            Re::Union(a, b) => a.nullable() || b.nullable(),
+            Re::Comp(r) => !r.nullable(),
+            Re::Loop(r, lo, _) => *lo == 0 || r.nullable(),
+        }
+    }
+
+    /// The language `{ w | c w ∈ self }`
+    pub fn derive(&self, c: char) -> Re {
+        match self {
+            Re::Empty | Re::Epsilon => Re::Empty,
+            Re::Range(lo, hi) if *lo <= c && c <= *hi => Re::Epsilon,
+            Re::Range(_, _) => Re::Empty,
+            Re::Concat(a, b) => {
+                let first = Re::concat(a.derive(c), (**b).clone());
+                if a.nullable() {
+                    Re::union(first, b.derive(c))
+                } else {
+                    first
+                }
+            }
+            Re::Union(a, b) => Re::union(a.derive(c), b.derive(c)),
+            Re::Inter(a, b) => Re::inter(a.derive(c), b.derive(c)),
+            Re::Star(r) => Re::concat(r.derive(c), self.clone()),
+            Re::Comp(r) => Re::comp(r.derive(c)),
+            // a nullable `r` may repeat fewer times, so the first repetition
+            // to consume `c` may as well be the first one
+            Re::Loop(r, lo, hi) => Re::concat(
+                r.derive(c),
+                Re::power((**r).clone(), lo.saturating_sub(1), hi - 1),
+            ),
+        }
+    }
+
+    pub fn matches(&self, s: &str) -> bool {
+        s.chars()
+            .try_fold(self.clone(), |r, c| {
+                let d = r.derive(c);
+                (d != Re::Empty).then_some(d)
+            })
+            .is_some_and(|r| r.nullable())
+    }
+
+    /// The byte range of the shortest leftmost substring of `s` in the
+    /// language, skipping the empty string if `non_empty` is set.
+    pub fn find_shortest(&self, s: &str, non_empty: bool) -> Option<(usize, usize)> {
+        let ends = |start: usize| s[start..].char_indices().map(move |(b, c)| (start + b, c));
+        let starts = s.char_indices().map(|(b, _)| b).chain([s.len()]);
+        for start in starts {
+            if !non_empty && self.nullable() {
+                return Some((start, start));
+            }
+            let mut r = self.clone();
+            for (b, c) in ends(start) {
+                r = r.derive(c);
+                if r == Re::Empty {
+                    break;
+                }
+                if r.nullable() {
+                    return Some((start, b + c.len_utf8()));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl fmt::Display for Re {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Re::Empty => write!(f, "∅"),
+            Re::Epsilon => write!(f, "ε"),
+            Re::Range(lo, hi) if lo == hi => write!(f, "{:?}", lo),
+            Re::Range(lo, hi) => write!(f, "[{:?}-{:?}]", lo, hi),
+            Re::Concat(a, b) => write!(f, "({}{})", a, b),
+            Re::Union(a, b) => write!(f, "({}|{})", a, b),
+            Re::Inter(a, b) => write!(f, "({}&{})", a, b),
+            Re::Star(r) => write!(f, "{}*", r),
+            Re::Comp(r) => write!(f, "~{}", r),
+            Re::Loop(r, lo, hi) => write!(f, "{}{{{},{}}}", r, lo, hi),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn membership() {
+        let ab = Re::star(Re::union(Re::literal("a"), Re::literal("b")));
+        assert!(ab.matches(""));
+        assert!(ab.matches("abba"));
+        assert!(!ab.matches("abc"));
+
+        let digits = Re::concat(Re::range('0', '9'), Re::star(Re::range('0', '9')));
+        assert!(digits.matches("2024"));
+        assert!(!digits.matches(""));
+        assert!(Re::all().matches("αβγ"));
+        assert!(!Re::range('b', 'a').matches("a"));
+
+        // repetitions stay counted, however many they are
+        let ab = Re::power(Re::literal("ab"), 2, 1000);
+        assert!(matches!(ab, Re::Loop(_, 2, 1000)));
+        assert!(!ab.matches("ab"));
+        assert!(ab.matches("abab"));
+        assert!(ab.matches(&"ab".repeat(1000)));
+        assert!(!ab.matches(&"ab".repeat(1001)));
+        let a = Re::power(Re::star(Re::literal("a")), 3, 3);
+        assert!(a.matches("") && a.matches("aaaaa"));
+        assert_eq!(Re::power(Re::literal("a"), 2, 1), Re::Empty);
+    }
+
+    #[test]
+    fn intersection_and_complement() {
+        let even = Re::star(Re::concat(Re::all_char(), Re::all_char()));
+        let a_star = Re::star(Re::literal("a"));
+        let even_as = Re::inter(even.clone(), a_star.clone());
+        assert!(even_as.matches("aaaa"));
+        assert!(!even_as.matches("aaa"));
+        assert!(!even_as.matches("ab"));
+
+        let odd = Re::comp(even);
+        assert!(odd.matches("abc"));
+        assert!(!odd.matches("ab"));
+        assert!(!Re::inter(odd, Re::literal("aa")).matches("aa"));
+    }
+
+    #[test]
+    fn shortest_leftmost_match() {
+        let r = Re::concat(Re::literal("a"), Re::star(Re::literal("b")));
+        assert_eq!(r.find_shortest("xxabbb", false), Some((2, 3)));
+        assert_eq!(Re::star(r.clone()).find_shortest("xa", false), Some((0, 0)));
+        assert_eq!(Re::star(r).find_shortest("xa", true), Some((1, 2)));
+        assert_eq!(Re::literal("β").find_shortest("αβ", true), Some((2, 4)));
+    }
+}
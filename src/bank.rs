@@ -0,0 +1,268 @@
+use crate::language::{Eval, Expr};
+use std::collections::HashMap;
+use std::{iter, mem};
+
+/// A component with its rank, the order it was inserted in
+type Ranked = (usize, Expr);
+
+/// Enumerated components, evaluated once on the example inputs and indexed by
+/// sort and output vector, so that filling a hole is a single lookup.
+#[derive(Debug, Default)]
+pub struct Bank {
+    parameter: String,
+    inputs: Vec<Expr>,
+    outputs: HashMap<String, HashMap<Vec<Expr>, Ranked>>,
+    /// How many larger components to keep besides the smallest
+    extra: usize,
+    /// The larger components kept, smallest first
+    others: HashMap<(String, Vec<Expr>), Vec<Ranked>>,
+    /// How many components were inserted
+    inserted: usize,
+}
+
+impl Bank {
+    /// A bank with no components yet, for programs of `parameter` evaluated
+    /// on `inputs`
+    pub fn with_inputs(parameter: &str, inputs: Vec<Expr>) -> Self {
+        Bank {
+            parameter: parameter.to_owned(),
+            inputs,
+            ..Bank::default()
+        }
+    }
+
+    /// Keeps up to `n` components for each output vector, rather than only
+    /// the smallest, to offer alternatives that agree on the examples
+    pub fn with_alternatives(mut self, n: usize) -> Self {
+        self.extra = n.saturating_sub(1);
+        self
+    }
+
+    /// Evaluates every component, keeping the smallest for each output
+    /// vector, the earliest in enumeration order among equals. Components
+    /// that fail to evaluate on some input are dropped.
+    pub fn new(
+        components: &HashMap<String, Vec<Expr>>,
+        parameter: &str,
+        inputs: Vec<Expr>,
+    ) -> Self {
+        let mut bank = Bank::with_inputs(parameter, inputs);
+        for (sort, exprs) in components {
+            for e in exprs {
+                if let Ok(out) = evaluate(e, parameter, &bank.inputs) {
+                    bank.insert(sort, e.clone(), out);
+                }
+            }
+        }
+        bank
+    }
+
+    /// Adds component `e` of `sort`, already evaluated to `out` on the
+    /// inputs, unless an earlier one as small has the same outputs
+    pub fn insert(&mut self, sort: &str, e: Expr, out: Vec<Expr>) {
+        self.inserted += 1;
+        self.insert_ranked(sort, (self.inserted, e), out);
+    }
+
+    /// Adds component `e` with its rank, ordering components by size and
+    /// then rank
+    fn insert_ranked(&mut self, sort: &str, mut e: Ranked, out: Vec<Expr>) {
+        let index = self.outputs.entry(sort.to_owned()).or_default();
+        match index.get_mut(&out) {
+            Some(best) if order(best) <= order(&e) => {}
+            Some(best) => mem::swap(best, &mut e),
+            None => {
+                index.insert(out, e);
+                return;
+            }
+        }
+        // `e` is now the larger of the two
+        if self.extra > 0 {
+            let others = self.others.entry((sort.to_owned(), out)).or_default();
+            let at = others.partition_point(|o| order(o) <= order(&e));
+            others.insert(at, e);
+            others.truncate(self.extra);
+        }
+    }
+
+    /// Adds the component built by `e`, of `sort` and `size`, as
+    /// [`Bank::insert`] does, but only builds it if it would be kept
+    pub fn insert_with(
+        &mut self,
+        sort: &str,
+        size: usize,
+        out: Vec<Expr>,
+        e: impl FnOnce() -> Expr,
+    ) {
+        let kept = match self.outputs.get(sort).and_then(|index| index.get(&out)) {
+            Some((_, best)) if best.size() <= size => {
+                let key = (sort.to_owned(), out.clone());
+                let others = self.others.get(&key).map_or(&[][..], Vec::as_slice);
+                // only larger alternatives, or a free place, make room for it
+                others.len() < self.extra || others.iter().any(|(_, o)| o.size() > size)
+            }
+            _ => true,
+        };
+        if kept {
+            self.insert(sort, e(), out);
+        }
+    }
+
+    /// Evaluates the kept components on one more input, extending the output
+    /// vectors they are indexed by. Components that fail on it are dropped,
+    /// and the larger ones kept for an output vector may now have their own.
+    pub fn add_input(&mut self, input: Expr) {
+        let env = HashMap::from([(self.parameter.clone(), input.clone())]);
+        self.inputs.push(input);
+        let mut others = mem::take(&mut self.others);
+        for (sort, index) in mem::take(&mut self.outputs) {
+            for (out, best) in index {
+                let larger = others
+                    .remove(&(sort.clone(), out.clone()))
+                    .unwrap_or_default();
+                for (rank, e) in iter::once(best).chain(larger) {
+                    if let Ok(o) = e.clone().eval(&env) {
+                        let mut out = out.clone();
+                        out.push(o);
+                        self.insert_ranked(&sort, (rank, e), out);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The example inputs components are evaluated on
+    pub fn inputs(&self) -> &[Expr] {
+        &self.inputs
+    }
+
+    /// The outputs of `e` on the example inputs, if it evaluates on all
+    pub fn outputs(&self, e: &Expr) -> Option<Vec<Expr>> {
+        evaluate(e, &self.parameter, &self.inputs).ok()
+    }
+
+    /// Whether `e` maps each input of `ios` to its output
+    pub fn satisfies(&self, e: &Expr, ios: &[(Expr, Expr)]) -> bool {
+        let inputs: Vec<Expr> = ios.iter().map(|(i, _)| i.clone()).collect();
+        evaluate(e, &self.parameter, &inputs)
+            .is_ok_and(|out| out.iter().eq(ios.iter().map(|(_, o)| o)))
+    }
+
+    /// A component of `sort` consistent with every example in `ios`, the
+    /// smallest and then earliest inserted. Inverse semantics keep the inputs
+    /// of the root specification, so examples over any other inputs are
+    /// evaluated component by component: a lookup becomes a pass over every
+    /// component of `sort`, each evaluated on all of `ios`.
+    pub fn fill(&self, sort: &str, ios: &[(Expr, Expr)]) -> Option<&Expr> {
+        let index = self.outputs.get(sort)?;
+        if ios.iter().map(|(i, _)| i).eq(self.inputs.iter()) {
+            let out: Vec<Expr> = ios.iter().map(|(_, o)| o.clone()).collect();
+            return index.get(&out).map(|(_, e)| e);
+        }
+        index
+            .values()
+            .filter(|(_, e)| self.satisfies(e, ios))
+            .min_by_key(|ranked| order(ranked))
+            .map(|(_, e)| e)
+    }
+
+    /// Every kept component of `sort` consistent with `ios`, smallest first,
+    /// and at the cost of [`Bank::fill`] over other inputs
+    pub fn fills(&self, sort: &str, ios: &[(Expr, Expr)]) -> Vec<&Expr> {
+        let Some(index) = self.outputs.get(sort) else {
+            return Vec::new();
+        };
+        let others = |out: &Vec<Expr>| {
+            let key = (sort.to_owned(), out.clone());
+            self.others.get(&key).into_iter().flatten()
+        };
+        if ios.iter().map(|(i, _)| i).eq(self.inputs.iter()) {
+            let out: Vec<Expr> = ios.iter().map(|(_, o)| o.clone()).collect();
+            return index
+                .get(&out)
+                .into_iter()
+                .chain(others(&out))
+                .map(|(_, e)| e)
+                .collect();
+        }
+        let mut fills: Vec<&Ranked> = index
+            .iter()
+            .flat_map(|(out, best)| iter::once(best).chain(others(out)))
+            .filter(|(_, e)| self.satisfies(e, ios))
+            .collect();
+        fills.sort_by_key(|ranked| order(ranked));
+        fills.into_iter().map(|(_, e)| e).collect()
+    }
+}
+
+/// The order of ranked components: smallest first, then earliest inserted
+fn order((rank, e): &Ranked) -> (usize, usize) {
+    (e.size(), *rank)
+}
+
+fn evaluate(e: &Expr, parameter: &str, inputs: &[Expr]) -> Result<Vec<Expr>, String> {
+    inputs
+        .iter()
+        .map(|i| {
+            let env = HashMap::from([(parameter.to_owned(), i.clone())]);
+            e.clone().eval(&env)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::Func;
+
+    #[test]
+    fn fill_by_outputs() {
+        let name = || Expr::Var("name".into());
+        let len = Expr::call(Func::StrLen(name()));
+        let components = HashMap::from([
+            ("String".to_string(), vec![name()]),
+            ("Int".to_string(), vec![Expr::ConstInt(0), len.clone()]),
+        ]);
+        let inputs = vec![Expr::ConstStr("ab".into()), Expr::ConstStr("xyz".into())];
+        let bank = Bank::new(&components, "name", inputs.clone());
+
+        let ios: Vec<(Expr, Expr)> = inputs
+            .into_iter()
+            .zip([Expr::ConstInt(2), Expr::ConstInt(3)])
+            .collect();
+        assert_eq!(bank.fill("Int", &ios), Some(&len));
+        assert_eq!(bank.fill("Int", &ios[1..]), Some(&len));
+        assert_eq!(bank.fill("String", &ios), None);
+
+        // larger components with the same outputs are kept on request
+        let s = |s: &str| Expr::ConstStr(s.into());
+        let whole = Expr::call(Func::SubStr(name(), Expr::ConstInt(0), len));
+        let mut bank = Bank::with_inputs("name", vec![s("ab"), s("xyz")]).with_alternatives(2);
+        bank.insert("String", whole.clone(), vec![s("ab"), s("xyz")]);
+        bank.insert("String", name(), vec![s("ab"), s("xyz")]);
+        let ios = vec![(s("ab"), s("ab")), (s("xyz"), s("xyz"))];
+        assert_eq!(bank.fills("String", &ios), [&name(), &whole]);
+        // and a component that would not be kept is never built
+        bank.insert_with("String", whole.size(), vec![s("ab"), s("xyz")], || {
+            unreachable!("built a component that is not kept")
+        });
+
+        // a new input tells apart components that agreed on the others
+        let mut bank = Bank::with_inputs("name", vec![s("ab")]).with_alternatives(2);
+        bank.insert("String", name(), vec![s("ab")]);
+        bank.insert("String", s("ab"), vec![s("ab")]);
+        bank.add_input(s("xyz"));
+        let ios = vec![(s("ab"), s("ab")), (s("xyz"), s("ab"))];
+        assert_eq!(bank.fill("String", &ios), Some(&s("ab")));
+        let ios = vec![(s("ab"), s("ab")), (s("xyz"), s("xyz"))];
+        assert_eq!(bank.fills("String", &ios), [&name()]);
+
+        // equals over other inputs come in the order they were inserted
+        let mut bank = Bank::with_inputs("name", vec![s("ab")]);
+        bank.insert("String", name(), vec![s("ab")]);
+        bank.insert("String", s("xyz"), vec![s("xyz")]);
+        let ios = vec![(s("xyz"), s("xyz"))];
+        assert_eq!(bank.fill("String", &ios), Some(&name()));
+        assert_eq!(bank.fills("String", &ios), [&name(), &s("xyz")]);
+    }
+}
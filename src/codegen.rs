@@ -0,0 +1,1033 @@
+use crate::language::{Expr, Func, RegFun, RegLang};
+use crate::sygus::Sort;
+use std::collections::{BTreeSet, HashMap};
+
+/// The sort of a value of a program
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Ty {
+    Str,
+    Int,
+    Bool,
+}
+
+impl Ty {
+    fn of_sort(sort: &Sort) -> Result<Ty, String> {
+        match sort.to_string().as_str() {
+            "String" => Ok(Ty::Str),
+            "Int" => Ok(Ty::Int),
+            "Bool" => Ok(Ty::Bool),
+            sort => Err(format!("unsupported sort: {}", sort)),
+        }
+    }
+}
+
+/// The sort of `e`, given the sorts of its variables
+fn ty(e: &Expr, vars: &HashMap<&str, Ty>) -> Result<Ty, String> {
+    use Func::*;
+    match e {
+        Expr::ConstStr(_) => Ok(Ty::Str),
+        Expr::ConstInt(_) => Ok(Ty::Int),
+        Expr::ConstBool(_) => Ok(Ty::Bool),
+        Expr::Var(x) => vars
+            .get(x.as_str())
+            .copied()
+            .ok_or_else(|| format!("unknown variable: {}", x)),
+        Expr::If(_, t, _) => ty(t, vars),
+        Expr::Call(f) => Ok(match **f {
+            Append(..) | StrAt(..) | SubStr(..) | Replace(..) | ReplaceAll(..) | ReplaceRe(..)
+            | ReplaceReAll(..) | FromCode(..) | IntToStr(..) => Ty::Str,
+            StrLen(..) | Index(..) | ToCode(..) | Add(..) | Min(..) | Mult(..) | Div(..)
+            | Abs(..) | Mod(..) | NegI(..) | StrToInt(..) => Ty::Int,
+            _ => Ty::Bool,
+        }),
+    }
+}
+
+/// A standalone Rust function `name` computing `body` over `parameters`,
+/// with the semantics of the interpreter. Strings are passed as `&str` and
+/// returned as `String`, integers are `i64`. Where the interpreter fails,
+/// on division by zero or an integer too large for `str.to.int`, the
+/// function panics. The helpers it needs are nested inside it, so that
+/// several generated functions can be `include!`d side by side.
+pub fn rust(name: &str, parameters: &[(String, Sort)], body: &Expr) -> Result<String, String> {
+    let mut generator = Rust::default();
+    let mut signature = Vec::new();
+    for (parameter, sort) in parameters {
+        let ty = Ty::of_sort(sort)?;
+        // parameters must not shadow the helpers, nor each other
+        let mut ident = ident(parameter, RUST_KEYWORDS);
+        while RUST_HELPERS.contains(&ident.as_str())
+            || generator.idents.values().any(|i| *i == ident)
+        {
+            ident.push('_');
+        }
+        signature.push(format!(
+            "{}: {}",
+            ident,
+            ["&str", "i64", "bool"][ty as usize]
+        ));
+        generator.vars.insert(parameter.as_str(), ty);
+        generator.idents.insert(parameter.as_str(), ident);
+    }
+    let return_ty = ty(body, &generator.vars)?;
+    let body = generator.value(body)?;
+
+    let mut code = format!(
+        "pub fn {}({}) -> {} {{\n",
+        ident(name, RUST_KEYWORDS),
+        signature.join(", "),
+        ["String", "i64", "bool"][return_ty as usize]
+    );
+    for helper in &generator.helpers {
+        for line in helper_source(helper).lines() {
+            code.push_str("    ");
+            code.push_str(line);
+            code.push('\n');
+        }
+        code.push('\n');
+    }
+    code.push_str(&format!("    {}\n}}\n", body));
+    Ok(code)
+}
+
+/// An identifier for a SyGuS symbol, apart from the `keywords` of the
+/// target language
+fn ident(symbol: &str, keywords: &[&str]) -> String {
+    let mut ident: String = symbol
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !ident.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        ident.insert(0, '_');
+    }
+    if keywords.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+    ident
+}
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for",
+    "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+    "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where",
+    "while", "async", "await", "dyn", "_",
+];
+
+/// The items nested in a generated Rust function, which parameters must not
+/// shadow
+const RUST_HELPERS: &[&str] = &[
+    "at",
+    "substr",
+    "index_of",
+    "replace",
+    "replace_all",
+    "replace_re",
+    "replace_re_all",
+    "is_digit",
+    "to_code",
+    "from_code",
+    "div",
+    "modulo",
+    "str_to_int",
+    "int_to_str",
+    "Re",
+    "MAX_CHAR",
+];
+
+#[derive(Default)]
+struct Rust<'a> {
+    vars: HashMap<&'a str, Ty>,
+    idents: HashMap<&'a str, String>,
+    /// The helpers used so far, emitted in a fixed order
+    helpers: BTreeSet<&'static str>,
+}
+
+impl Rust<'_> {
+    fn call(&mut self, helper: &'static str, args: &[String]) -> String {
+        self.helpers.insert(helper);
+        if helper == "at" {
+            self.helpers.insert("substr");
+        }
+        format!("{}({})", helper, args.join(", "))
+    }
+
+    /// An owned value: a `String`, `i64` or `bool`
+    fn value(&mut self, e: &Expr) -> Result<String, String> {
+        use Func::*;
+        Ok(match e {
+            Expr::ConstStr(s) => format!("String::from({:?})", s),
+            Expr::ConstInt(n) => format!("{}i64", n),
+            Expr::ConstBool(b) => b.to_string(),
+            Expr::Var(x) => match self.vars.get(x.as_str()) {
+                Some(Ty::Str) => format!("{}.to_string()", self.idents[x.as_str()]),
+                Some(_) => self.idents[x.as_str()].clone(),
+                None => return Err(format!("unknown variable: {}", x)),
+            },
+            Expr::If(c, t, e) => {
+                let (c, t) = (self.value(c)?, self.value(t)?);
+                match &**e {
+                    Expr::If(..) => format!("if {} {{ {} }} else {}", c, t, self.value(e)?),
+                    e => format!("if {} {{ {} }} else {{ {} }}", c, t, self.value(e)?),
+                }
+            }
+            Expr::Call(f) => match &**f {
+                Append(a, b) => format!("[{}, {}].concat()", self.str(a)?, self.str(b)?),
+                StrLen(a) => format!("{}.chars().count() as i64", self.str(a)?),
+                StrAt(a, b) => {
+                    let args = [self.str(a)?, self.value(b)?];
+                    self.call("at", &args)
+                }
+                SubStr(a, b, c) => {
+                    let args = [self.str(a)?, self.value(b)?, self.value(c)?];
+                    self.call("substr", &args)
+                }
+                IsPre(a, b) => format!("{}.starts_with({})", self.str(b)?, self.str(a)?),
+                IsPost(a, b) => format!("{}.ends_with({})", self.str(b)?, self.str(a)?),
+                Contains(a, b) => format!("{}.contains({})", self.str(a)?, self.str(b)?),
+                Index(a, b, c) => {
+                    let args = [self.str(a)?, self.str(b)?, self.value(c)?];
+                    self.call("index_of", &args)
+                }
+                Replace(a, b, c) => {
+                    let args = [self.str(a)?, self.str(b)?, self.str(c)?];
+                    self.call("replace", &args)
+                }
+                ReplaceAll(a, b, c) => {
+                    let args = [self.str(a)?, self.str(b)?, self.str(c)?];
+                    self.call("replace_all", &args)
+                }
+                ReplaceRe(a, r, c) => {
+                    let args = [self.str(a)?, format!("&{}", self.re(r)?), self.str(c)?];
+                    self.call("replace_re", &args)
+                }
+                ReplaceReAll(a, r, c) => {
+                    let args = [self.str(a)?, format!("&{}", self.re(r)?), self.str(c)?];
+                    self.call("replace_re_all", &args)
+                }
+                InRe(a, r) => format!("{}.matches({})", self.re(r)?, self.str(a)?),
+                IsDigit(a) => {
+                    let args = [self.str(a)?];
+                    self.call("is_digit", &args)
+                }
+                ToCode(a) => {
+                    let args = [self.str(a)?];
+                    self.call("to_code", &args)
+                }
+                FromCode(a) => {
+                    let args = [self.value(a)?];
+                    self.call("from_code", &args)
+                }
+                Leq(a, b) => self.binary(a, "<=", b)?,
+                Geq(a, b) => self.binary(a, ">=", b)?,
+                Lt(a, b) => self.binary(a, "<", b)?,
+                Gt(a, b) => self.binary(a, ">", b)?,
+                Eql(a, b) => self.binary(a, "==", b)?,
+                Add(a, b) => self.binary(a, "+", b)?,
+                Min(a, b) => self.binary(a, "-", b)?,
+                Mult(a, b) => self.binary(a, "*", b)?,
+                Div(a, b) => {
+                    let args = [self.value(a)?, self.value(b)?];
+                    self.call("div", &args)
+                }
+                Mod(a, b) => {
+                    let args = [self.value(a)?, self.value(b)?];
+                    self.call("modulo", &args)
+                }
+                Abs(a) => format!("i64::abs({})", self.value(a)?),
+                NegI(a) => format!("-{}", self.operand(a)?),
+                NegB(a) => format!("!{}", self.operand(a)?),
+                // both sides are evaluated, as the interpreter does
+                And(a, b) => self.binary(a, "&", b)?,
+                Or(a, b) => self.binary(a, "|", b)?,
+                Xor(a, b) => self.binary(a, "^", b)?,
+                Implies(a, b) => format!("!{} | {}", self.operand(a)?, self.operand(b)?),
+                LexEq(a, b) => self.binary(a, "==", b)?,
+                LexLt(a, b) => self.binary(a, "<", b)?,
+                LexLeq(a, b) => self.binary(a, "<=", b)?,
+                LexGeq(a, b) => self.binary(a, ">=", b)?,
+                StrToInt(a) => {
+                    let args = [self.str(a)?];
+                    self.call("str_to_int", &args)
+                }
+                IntToStr(a) => {
+                    let args = [self.value(a)?];
+                    self.call("int_to_str", &args)
+                }
+            },
+        })
+    }
+
+    /// Strings are compared as `&str`, to save copying them
+    fn binary(&mut self, a: &Expr, op: &str, b: &Expr) -> Result<String, String> {
+        if ty(a, &self.vars)? == Ty::Str {
+            return Ok(format!("{} {} {}", self.str(a)?, op, self.str(b)?));
+        }
+        Ok(format!("{} {} {}", self.operand(a)?, op, self.operand(b)?))
+    }
+
+    /// A value as the operand of an operator, in parentheses unless it is
+    /// atomic or a call
+    fn operand(&mut self, e: &Expr) -> Result<String, String> {
+        use Func::*;
+        let value = self.value(e)?;
+        Ok(match e {
+            Expr::ConstInt(n) if *n >= 0 => value,
+            Expr::ConstStr(_) | Expr::ConstBool(_) | Expr::Var(_) => value,
+            Expr::Call(f)
+                if !matches!(
+                    **f,
+                    StrLen(..)
+                        | Leq(..)
+                        | Geq(..)
+                        | Lt(..)
+                        | Gt(..)
+                        | Eql(..)
+                        | Add(..)
+                        | Min(..)
+                        | Mult(..)
+                        | NegI(..)
+                        | NegB(..)
+                        | And(..)
+                        | Or(..)
+                        | Xor(..)
+                        | Implies(..)
+                        | LexEq(..)
+                        | LexLt(..)
+                        | LexLeq(..)
+                        | LexGeq(..)
+                ) =>
+            {
+                value
+            }
+            _ => format!("({})", value),
+        })
+    }
+
+    /// A string as a `&str`, borrowed from a parameter or literal if it can
+    fn str(&mut self, e: &Expr) -> Result<String, String> {
+        Ok(match e {
+            Expr::ConstStr(s) => format!("{:?}", s),
+            Expr::Var(x) if self.vars.get(x.as_str()) == Some(&Ty::Str) => {
+                self.idents[x.as_str()].clone()
+            }
+            Expr::If(..) => format!("({}).as_str()", self.value(e)?),
+            e => format!("{}.as_str()", self.value(e)?),
+        })
+    }
+
+    /// A regular expression, as a value of the `Re` helper
+    fn re(&mut self, r: &RegLang) -> Result<String, String> {
+        self.helpers.insert("re");
+        Ok(match r {
+            RegLang::Nil => "Re::Empty".to_string(),
+            RegLang::All => "Re::star(Re::Range('\\0', MAX_CHAR))".to_string(),
+            RegLang::AllChar => "Re::Range('\\0', MAX_CHAR)".to_string(),
+            RegLang::Var(name) => return Err(format!("unsupported RegLan parameter: {}", name)),
+            RegLang::RCall(f) => match &**f {
+                RegFun::Sequence(a, b) => format!("Re::concat({}, {})", self.re(a)?, self.re(b)?),
+                RegFun::Union(a, b) => format!("Re::union({}, {})", self.re(a)?, self.re(b)?),
+                RegFun::Inter(a, b) => format!("Re::inter({}, {})", self.re(a)?, self.re(b)?),
+                RegFun::Star(a) => format!("Re::star({})", self.re(a)?),
+                RegFun::One(a) => format!("Re::plus({})", self.re(a)?),
+                RegFun::Opt(a) => format!("Re::union({}, Re::Epsilon)", self.re(a)?),
+                RegFun::Range(a, b) => format!("Re::range({}, {})", self.str(a)?, self.str(b)?),
+                RegFun::FromStr(a) => format!("Re::literal({})", self.str(a)?),
+                RegFun::Comp(a) => format!("Re::comp({})", self.re(a)?),
+                RegFun::Diff(a, b) => {
+                    format!("Re::inter({}, Re::comp({}))", self.re(a)?, self.re(b)?)
+                }
+                RegFun::Power(a, n) => format!("Re::power({}, {}, {})", self.re(a)?, n, n),
+                RegFun::Loop(a, lo, hi) => format!("Re::power({}, {}, {})", self.re(a)?, lo, hi),
+            },
+        })
+    }
+}
+
+/// The source of a helper, written as the interpreter computes it
+fn helper_source(helper: &str) -> &'static str {
+    match helper {
+        "at" => {
+            "fn at(s: &str, i: i64) -> String {
+    substr(s, i, 1)
+}"
+        }
+        "substr" => {
+            "fn substr(s: &str, i: i64, n: i64) -> String {
+    if i < 0 || n <= 0 {
+        return String::new();
+    }
+    s.chars().skip(i as usize).take(n as usize).collect()
+}"
+        }
+        "index_of" => {
+            "fn index_of(s: &str, t: &str, i: i64) -> i64 {
+    if i < 0 || i > s.chars().count() as i64 {
+        return -1;
+    }
+    let start = s.char_indices().nth(i as usize).map_or(s.len(), |(b, _)| b);
+    match s[start..].find(t) {
+        Some(k) => i + s[start..start + k].chars().count() as i64,
+        None => -1,
+    }
+}"
+        }
+        "replace" => {
+            "fn replace(s: &str, t: &str, u: &str) -> String {
+    if t.is_empty() {
+        [u, s].concat()
+    } else {
+        s.replacen(t, u, 1)
+    }
+}"
+        }
+        "replace_all" => {
+            "fn replace_all(s: &str, t: &str, u: &str) -> String {
+    if t.is_empty() {
+        s.to_string()
+    } else {
+        s.replace(t, u)
+    }
+}"
+        }
+        "replace_re" => {
+            "fn replace_re(s: &str, r: &Re, u: &str) -> String {
+    match r.find_shortest(s, false) {
+        Some((start, end)) => [&s[..start], u, &s[end..]].concat(),
+        None => s.to_string(),
+    }
+}"
+        }
+        "replace_re_all" => {
+            "fn replace_re_all(s: &str, r: &Re, u: &str) -> String {
+    let mut result = String::new();
+    let mut rest = s;
+    while let Some((start, end)) = r.find_shortest(rest, true) {
+        result.push_str(&rest[..start]);
+        result.push_str(u);
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}"
+        }
+        "is_digit" => {
+            "fn is_digit(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!((chars.next(), chars.next()), (Some('0'..='9'), None))
+}"
+        }
+        "to_code" => {
+            "fn to_code(s: &str) -> i64 {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => c as i64,
+        _ => -1,
+    }
+}"
+        }
+        "from_code" => {
+            "fn from_code(n: i64) -> String {
+    u32::try_from(n)
+        .ok()
+        .filter(|n| *n <= 0x2FFFF)
+        .and_then(char::from_u32)
+        .map_or(String::new(), String::from)
+}"
+        }
+        "div" => {
+            "fn div(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        panic!(\"Div: division by zero\");
+    }
+    a.div_euclid(b)
+}"
+        }
+        "modulo" => {
+            "fn modulo(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        panic!(\"Mod: division by zero\");
+    }
+    a.rem_euclid(b)
+}"
+        }
+        "str_to_int" => {
+            "fn str_to_int(s: &str) -> i64 {
+    if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) {
+        return -1;
+    }
+    s.parse().expect(\"StrToInt: integer overflow\")
+}"
+        }
+        "int_to_str" => {
+            "fn int_to_str(n: i64) -> String {
+    if n < 0 {
+        String::new()
+    } else {
+        n.to_string()
+    }
+}"
+        }
+        "re" => RE,
+        _ => unreachable!("no helper {}", helper),
+    }
+}
+
+/// Regular languages over code points, matched with Brzozowski derivatives
+const RE: &str = "#[allow(dead_code)]
+const MAX_CHAR: char = '\\u{2FFFF}';
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Re {
+    Empty,
+    Epsilon,
+    Range(char, char),
+    Concat(Box<Re>, Box<Re>),
+    Union(Box<Re>, Box<Re>),
+    Inter(Box<Re>, Box<Re>),
+    Star(Box<Re>),
+    Comp(Box<Re>),
+    Loop(Box<Re>, u32, u32),
+}
+
+#[allow(dead_code)]
+impl Re {
+    fn literal(s: &str) -> Re {
+        s.chars().map(|c| Re::Range(c, c)).fold(Re::Epsilon, Re::concat)
+    }
+
+    fn range(lo: &str, hi: &str) -> Re {
+        let (mut lo, mut hi) = (lo.chars(), hi.chars());
+        match (lo.next(), lo.next(), hi.next(), hi.next()) {
+            (Some(lo), None, Some(hi), None) if lo <= hi => Re::Range(lo, hi),
+            _ => Re::Empty,
+        }
+    }
+
+    fn concat(a: Re, b: Re) -> Re {
+        match (a, b) {
+            (Re::Empty, _) | (_, Re::Empty) => Re::Empty,
+            (Re::Epsilon, r) | (r, Re::Epsilon) => r,
+            (a, b) => Re::Concat(Box::new(a), Box::new(b)),
+        }
+    }
+
+    fn union(a: Re, b: Re) -> Re {
+        match (a, b) {
+            (Re::Empty, r) | (r, Re::Empty) => r,
+            (a, b) if a == b => a,
+            (a, b) if a > b => Re::Union(Box::new(b), Box::new(a)),
+            (a, b) => Re::Union(Box::new(a), Box::new(b)),
+        }
+    }
+
+    fn inter(a: Re, b: Re) -> Re {
+        match (a, b) {
+            (Re::Empty, _) | (_, Re::Empty) => Re::Empty,
+            (a, b) if a == b => a,
+            (a, b) if a > b => Re::Inter(Box::new(b), Box::new(a)),
+            (a, b) => Re::Inter(Box::new(a), Box::new(b)),
+        }
+    }
+
+    fn star(r: Re) -> Re {
+        match r {
+            Re::Empty | Re::Epsilon => Re::Epsilon,
+            r @ Re::Star(_) => r,
+            r => Re::Star(Box::new(r)),
+        }
+    }
+
+    fn plus(r: Re) -> Re {
+        Re::concat(r.clone(), Re::star(r))
+    }
+
+    fn comp(r: Re) -> Re {
+        match r {
+            Re::Comp(r) => *r,
+            r => Re::Comp(Box::new(r)),
+        }
+    }
+
+    /// `r` repeated between `lo` and `hi` times
+    fn power(r: Re, lo: u32, hi: u32) -> Re {
+        match r {
+            _ if lo > hi => Re::Empty,
+            _ if hi == 0 => Re::Epsilon,
+            Re::Empty if lo == 0 => Re::Epsilon,
+            Re::Empty => Re::Empty,
+            Re::Epsilon => Re::Epsilon,
+            r if (lo, hi) == (1, 1) => r,
+            r => Re::Loop(Box::new(r), lo, hi),
+        }
+    }
+
+    fn nullable(&self) -> bool {
+        match self {
+            Re::Empty | Re::Range(_, _) => false,
+            Re::Epsilon | Re::Star(_) => true,
+            Re::Concat(a, b) | Re::Inter(a, b) => a.nullable() && b.nullable(),
+            Re::Union(a, b) => a.nullable() || b.nullable(),
+            Re::Comp(r) => !r.nullable(),
+            Re::Loop(r, lo, _) => *lo == 0 || r.nullable(),
+        }
+    }
+
+    fn derive(&self, c: char) -> Re {
+        match self {
+            Re::Empty | Re::Epsilon => Re::Empty,
+            Re::Range(lo, hi) if *lo <= c && c <= *hi => Re::Epsilon,
+            Re::Range(_, _) => Re::Empty,
+            Re::Concat(a, b) => {
+                let first = Re::concat(a.derive(c), (**b).clone());
+                if a.nullable() {
+                    Re::union(first, b.derive(c))
+                } else {
+                    first
+                }
+            }
+            Re::Union(a, b) => Re::union(a.derive(c), b.derive(c)),
+            Re::Inter(a, b) => Re::inter(a.derive(c), b.derive(c)),
+            Re::Star(r) => Re::concat(r.derive(c), self.clone()),
+            Re::Comp(r) => Re::comp(r.derive(c)),
+            Re::Loop(r, lo, hi) => Re::concat(
+                r.derive(c),
+                Re::power((**r).clone(), lo.saturating_sub(1), hi - 1),
+            ),
+        }
+    }
+
+    fn matches(&self, s: &str) -> bool {
+        s.chars().fold(self.clone(), |r, c| r.derive(c)).nullable()
+    }
+
+    /// The byte range of the shortest leftmost match, skipping the empty
+    /// string if `non_empty` is set
+    fn find_shortest(&self, s: &str, non_empty: bool) -> Option<(usize, usize)> {
+        for start in s.char_indices().map(|(b, _)| b).chain([s.len()]) {
+            if !non_empty && self.nullable() {
+                return Some((start, start));
+            }
+            let mut r = self.clone();
+            for (b, c) in s[start..].char_indices() {
+                r = r.derive(c);
+                if r == Re::Empty {
+                    break;
+                }
+                if r.nullable() {
+                    return Some((start, start + b + c.len_utf8()));
+                }
+            }
+        }
+        None
+    }
+}";
+
+const PYTHON_KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+/// A Python expression computing `body` from variables named after the
+/// `parameters`, with the semantics of the interpreter. Operators whose
+/// edge cases differ from Python's are applied as inline lambdas, so the
+/// expression needs no imports. Where the interpreter fails on division by
+/// zero, so does the expression. Regular expressions are not supported.
+pub fn python(parameters: &[(String, Sort)], body: &Expr) -> Result<String, String> {
+    let names: HashMap<&str, String> = parameters
+        .iter()
+        .map(|(p, _)| (p.as_str(), ident(p, PYTHON_KEYWORDS)))
+        .collect();
+    py(body, &names)
+}
+
+/// The name a parameter of `python` gets
+pub fn python_name(parameter: &str) -> String {
+    ident(parameter, PYTHON_KEYWORDS)
+}
+
+fn py(e: &Expr, names: &HashMap<&str, String>) -> Result<String, String> {
+    use Func::*;
+    let lambda = |body: &str, args: &[&Expr]| -> Result<String, String> {
+        let args: Result<Vec<String>, String> = args.iter().map(|a| py(a, names)).collect();
+        Ok(format!("(lambda {})({})", body, args?.join(", ")))
+    };
+    let binary = |a: &Expr, op: &str, b: &Expr| -> Result<String, String> {
+        Ok(format!("({} {} {})", py(a, names)?, op, py(b, names)?))
+    };
+    Ok(match e {
+        Expr::ConstStr(s) => python_string(s),
+        Expr::ConstInt(n) if *n < 0 => format!("({})", n),
+        Expr::ConstInt(n) => n.to_string(),
+        Expr::ConstBool(b) => if *b { "True" } else { "False" }.to_string(),
+        Expr::Var(x) => names
+            .get(x.as_str())
+            .cloned()
+            .ok_or_else(|| format!("unknown variable: {}", x))?,
+        Expr::If(c, t, e) => format!(
+            "({} if {} else {})",
+            py(t, names)?,
+            py(c, names)?,
+            py(e, names)?
+        ),
+        Expr::Call(f) => match &**f {
+            Append(a, b) => binary(a, "+", b)?,
+            StrLen(a) => format!("len({})", py(a, names)?),
+            StrAt(a, b) => lambda("s, i: s[i:i + 1] if i >= 0 else \"\"", &[a, b])?,
+            SubStr(a, b, c) => lambda(
+                "s, i, n: s[i:i + n] if i >= 0 and n > 0 else \"\"",
+                &[a, b, c],
+            )?,
+            IsPre(a, b) => format!("{}.startswith({})", py(b, names)?, py(a, names)?),
+            IsPost(a, b) => format!("{}.endswith({})", py(b, names)?, py(a, names)?),
+            Contains(a, b) => binary(b, "in", a)?,
+            Index(a, b, c) => lambda(
+                "s, t, i: s.find(t, i) if 0 <= i <= len(s) else -1",
+                &[a, b, c],
+            )?,
+            // unlike `str.replace` with no count, this puts `u` in front
+            // when `t` is empty, as SMT-LIB does
+            Replace(a, b, c) => format!(
+                "{}.replace({}, {}, 1)",
+                py(a, names)?,
+                py(b, names)?,
+                py(c, names)?
+            ),
+            ReplaceAll(a, b, c) => lambda("s, t, u: s.replace(t, u) if t else s", &[a, b, c])?,
+            ReplaceRe(..) | ReplaceReAll(..) | InRe(..) => {
+                return Err("regular expressions cannot be exported to Python".to_string())
+            }
+            IsDigit(a) => lambda("s: len(s) == 1 and \"0\" <= s <= \"9\"", &[a])?,
+            ToCode(a) => lambda("s: ord(s) if len(s) == 1 else -1", &[a])?,
+            FromCode(a) => lambda(
+                "n: chr(n) if 0 <= n <= 0x2FFFF and not 0xD800 <= n <= 0xDFFF else \"\"",
+                &[a],
+            )?,
+            Leq(a, b) | LexLeq(a, b) => binary(a, "<=", b)?,
+            Geq(a, b) | LexGeq(a, b) => binary(a, ">=", b)?,
+            Lt(a, b) | LexLt(a, b) => binary(a, "<", b)?,
+            Gt(a, b) => binary(a, ">", b)?,
+            Eql(a, b) | LexEq(a, b) => binary(a, "==", b)?,
+            Add(a, b) => binary(a, "+", b)?,
+            Min(a, b) => binary(a, "-", b)?,
+            Mult(a, b) => binary(a, "*", b)?,
+            // integer division and remainder are euclidean
+            Div(a, b) => lambda("a, b: a // b if b > 0 else -(a // -b)", &[a, b])?,
+            Mod(a, b) => format!("({} % abs({}))", py(a, names)?, py(b, names)?),
+            Abs(a) => format!("abs({})", py(a, names)?),
+            NegI(a) => format!("(-{})", py(a, names)?),
+            NegB(a) => format!("(not {})", py(a, names)?),
+            // both sides are evaluated, as the interpreter does
+            And(a, b) => binary(a, "&", b)?,
+            Or(a, b) => binary(a, "|", b)?,
+            Xor(a, b) => binary(a, "^", b)?,
+            Implies(a, b) => format!("((not {}) | {})", py(a, names)?, py(b, names)?),
+            StrToInt(a) => lambda("s: int(s) if s.isascii() and s.isdigit() else -1", &[a])?,
+            IntToStr(a) => lambda("n: str(n) if n >= 0 else \"\"", &[a])?,
+        },
+    })
+}
+
+/// A Python string literal
+fn python_string(s: &str) -> String {
+    let mut literal = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            c if c.is_control() => literal.push_str(&format!("\\U{:08x}", c as u32)),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// An ANSI SQL expression computing `body` from columns named after the
+/// `parameters`, with the semantics of the interpreter: positions count from
+/// 0, out of range `str.substr` is `''`, `str.replace` replaces only the
+/// first occurrence, and division is euclidean. Comparing strings assumes a
+/// binary collation. Regular expressions and character codes are not
+/// supported.
+pub fn sql(parameters: &[(String, Sort)], body: &Expr) -> Result<String, String> {
+    let names: HashMap<&str, String> = parameters
+        .iter()
+        .map(|(p, _)| (p.as_str(), format!("\"{}\"", p.replace('"', "\"\""))))
+        .collect();
+    to_sql(body, &names)
+}
+
+fn to_sql(e: &Expr, names: &HashMap<&str, String>) -> Result<String, String> {
+    use Func::*;
+    let substr = |s: &str, i: &str, n: &str| {
+        format!(
+            "CASE WHEN {i} >= 0 AND {n} > 0 THEN SUBSTRING({s} FROM {i} + 1 FOR {n}) ELSE '' END"
+        )
+    };
+    Ok(match e {
+        Expr::ConstStr(s) => format!("'{}'", s.replace('\'', "''")),
+        Expr::ConstInt(n) if *n < 0 => format!("({})", n),
+        Expr::ConstInt(n) => n.to_string(),
+        Expr::ConstBool(b) => if *b { "TRUE" } else { "FALSE" }.to_string(),
+        Expr::Var(x) => names
+            .get(x.as_str())
+            .cloned()
+            .ok_or_else(|| format!("unknown variable: {}", x))?,
+        Expr::If(c, t, e) => format!(
+            "(CASE WHEN {} THEN {} ELSE {} END)",
+            to_sql(c, names)?,
+            to_sql(t, names)?,
+            to_sql(e, names)?
+        ),
+        Expr::Call(f) => {
+            let args: Vec<String> = f
+                .args()
+                .into_iter()
+                .map(|a| to_sql(a, names))
+                .collect::<Result<_, _>>()?;
+            let binary = |op| format!("({} {} {})", args[0], op, args[1]);
+            match &**f {
+                Append(..) => format!("({} || {})", args[0], args[1]),
+                StrLen(..) => format!("CHAR_LENGTH({})", args[0]),
+                StrAt(..) => format!("({})", substr(&args[0], &args[1], "1")),
+                SubStr(..) => format!("({})", substr(&args[0], &args[1], &args[2])),
+                IsPre(..) => format!(
+                    "(SUBSTRING({1} FROM 1 FOR CHAR_LENGTH({0})) = {0})",
+                    args[0], args[1]
+                ),
+                IsPost(..) => format!(
+                    "(CHAR_LENGTH({0}) <= CHAR_LENGTH({1}) AND \
+                     SUBSTRING({1} FROM CHAR_LENGTH({1}) - CHAR_LENGTH({0}) + 1) = {0})",
+                    args[0], args[1]
+                ),
+                Contains(..) => format!("(POSITION({} IN {}) > 0)", args[1], args[0]),
+                Index(..) => {
+                    let (s, t, i) = (&args[0], &args[1], &args[2]);
+                    let position = format!("POSITION({t} IN SUBSTRING({s} FROM {i} + 1))");
+                    format!(
+                        "(CASE WHEN {i} < 0 OR {i} > CHAR_LENGTH({s}) OR {position} = 0 \
+                         THEN -1 ELSE {position} + {i} - 1 END)"
+                    )
+                }
+                Replace(..) => {
+                    let (s, t, u) = (&args[0], &args[1], &args[2]);
+                    let position = format!("POSITION({t} IN {s})");
+                    format!(
+                        "(CASE WHEN {position} = 0 THEN {s} ELSE \
+                         SUBSTRING({s} FROM 1 FOR {position} - 1) || {u} || \
+                         SUBSTRING({s} FROM {position} + CHAR_LENGTH({t})) END)"
+                    )
+                }
+                ReplaceAll(..) => format!(
+                    "(CASE WHEN {1} = '' THEN {0} ELSE REPLACE({0}, {1}, {2}) END)",
+                    args[0], args[1], args[2]
+                ),
+                ReplaceRe(..) | ReplaceReAll(..) | InRe(..) => {
+                    return Err("regular expressions cannot be exported to SQL".to_string())
+                }
+                ToCode(..) | FromCode(..) => {
+                    return Err("character codes cannot be exported to SQL".to_string())
+                }
+                IsDigit(..) => format!(
+                    "(CHAR_LENGTH({0}) = 1 AND {0} BETWEEN '0' AND '9')",
+                    args[0]
+                ),
+                Leq(..) | LexLeq(..) => binary("<="),
+                Geq(..) | LexGeq(..) => binary(">="),
+                Lt(..) | LexLt(..) => binary("<"),
+                Gt(..) => binary(">"),
+                Eql(..) | LexEq(..) => binary("="),
+                Add(..) => binary("+"),
+                Min(..) => binary("-"),
+                Mult(..) => binary("*"),
+                // `/` truncates and `MOD` takes the sign of the dividend, so
+                // negative remainders are moved up by the divisor
+                Div(..) => format!(
+                    "(CASE WHEN MOD({0}, {1}) < 0 THEN {0} / {1} - SIGN({1}) ELSE {0} / {1} END)",
+                    args[0], args[1]
+                ),
+                Mod(..) => format!(
+                    "(CASE WHEN MOD({0}, {1}) < 0 THEN MOD({0}, {1}) + ABS({1}) \
+                     ELSE MOD({0}, {1}) END)",
+                    args[0], args[1]
+                ),
+                Abs(..) => format!("ABS({})", args[0]),
+                NegI(..) => format!("(-{})", args[0]),
+                NegB(..) => format!("(NOT {})", args[0]),
+                And(..) => binary("AND"),
+                Or(..) => binary("OR"),
+                Xor(..) => binary("<>"),
+                Implies(..) => format!("((NOT {}) OR {})", args[0], args[1]),
+                StrToInt(..) => format!(
+                    "(CASE WHEN {0} SIMILAR TO '[0-9]+' THEN CAST({0} AS BIGINT) ELSE -1 END)",
+                    args[0]
+                ),
+                IntToStr(..) => format!(
+                    "(CASE WHEN {0} < 0 THEN '' ELSE CAST({0} AS VARCHAR(20)) END)",
+                    args[0]
+                ),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::Eval;
+
+    #[test]
+    fn compile_to_rust() {
+        let name = || Expr::Var("name".into());
+        let space = Expr::call(Func::Index(
+            name(),
+            Expr::ConstStr(" ".into()),
+            Expr::ConstInt(0),
+        ));
+        let program = Expr::call(Func::SubStr(name(), Expr::ConstInt(0), space));
+        let parameters = [("name".to_string(), Sort::Identifier("String".into()))];
+        let code = rust("f", &parameters, &program).unwrap();
+        assert!(code.starts_with("pub fn f(name: &str) -> String {\n"));
+        assert!(code.contains("    fn index_of(s: &str, t: &str, i: i64) -> i64 {\n"));
+        assert!(code.ends_with("    substr(name, 0i64, index_of(name, \" \", 0i64))\n}\n"));
+        assert!(!code.contains("fn replace"));
+    }
+
+    /// The interpreter's edge cases, compiled with `rustc` and run
+    #[test]
+    fn helpers_match_interpreter() {
+        use Func::*;
+        let (s, n) = (|s: &str| Expr::ConstStr(s.into()), Expr::ConstInt);
+        let lit = |t: &str| RegLang::RCall(Box::new(RegFun::FromStr(s(t))));
+        let cases = [
+            SubStr(s("abcdef"), n(1), n(3)),
+            SubStr(s("abcdef"), n(4), n(10)),
+            SubStr(s("abc"), n(3), n(1)),
+            SubStr(s("abc"), n(-1), n(2)),
+            SubStr(s("abc"), n(0), n(0)),
+            SubStr(s("äöü"), n(1), n(1)),
+            StrAt(s("αβγ"), n(2)),
+            StrAt(s("abc"), n(3)),
+            StrAt(s("abc"), n(-1)),
+            StrLen(s("αβγ")),
+            IsPre(s("abc"), s("ab")),
+            IsPost(s(""), s("abc")),
+            Index(s("abcabc"), s("c"), n(3)),
+            Index(s("abcabc"), s("d"), n(0)),
+            Index(s("abc"), s(""), n(3)),
+            Index(s("abc"), s(""), n(4)),
+            Index(s("abc"), s("a"), n(-1)),
+            Index(s("αβγ"), s("γ"), n(1)),
+            Replace(s("abab"), s("b"), s("x")),
+            Replace(s("abc"), s(""), s("x")),
+            ReplaceAll(s("abab"), s("b"), s("x")),
+            ReplaceAll(s("abc"), s(""), s("x")),
+            StrToInt(s("00123")),
+            StrToInt(s("-123")),
+            StrToInt(s("")),
+            StrToInt(s("9876543210")),
+            IntToStr(n(-123)),
+            IsDigit(s("7")),
+            IsDigit(s("77")),
+            ToCode(s("AB")),
+            FromCode(n(0x3B1)),
+            FromCode(n(0x30000)),
+            FromCode(n(-1)),
+            Div(n(-7), n(2)),
+            Mod(n(-7), n(2)),
+            Div(n(7), n(-2)),
+            Mod(n(7), n(-2)),
+            InRe(s("aaaa"), RegLang::RCall(Box::new(RegFun::Star(lit("aa"))))),
+            InRe(
+                s("ababab"),
+                RegLang::RCall(Box::new(RegFun::Loop(lit("ab"), 2, 500))),
+            ),
+            InRe(
+                s("ab"),
+                RegLang::RCall(Box::new(RegFun::Power(lit("ab"), 2))),
+            ),
+            ReplaceReAll(s("a.b.c"), lit("."), s("")),
+        ];
+        let mut code = String::from("fn main() {\n");
+        let mut expected = String::new();
+        for (i, case) in cases.into_iter().enumerate() {
+            let case = Expr::call(case);
+            let function = rust(&format!("case_{}", i), &[], &case).unwrap();
+            code = format!("{}{}", function, code);
+            code.push_str(&format!("    println!(\"{{:?}}\", case_{}());\n", i));
+            match case.eval(&HashMap::new()).unwrap() {
+                Expr::ConstStr(s) => expected.push_str(&format!("{:?}\n", s)),
+                Expr::ConstInt(n) => expected.push_str(&format!("{}\n", n)),
+                Expr::ConstBool(b) => expected.push_str(&format!("{}\n", b)),
+                e => panic!("not a value: {:?}", e),
+            }
+        }
+
+        // parameters named like helpers are renamed rather than shadow them
+        let string = || Sort::Identifier("String".into());
+        let parameters = [
+            ("at".to_string(), string()),
+            ("substr".to_string(), Sort::Identifier("Int".into())),
+            ("MAX_CHAR".to_string(), string()),
+        ];
+        let var = |x: &str| Expr::Var(x.into());
+        let body = Expr::call(Append(
+            Expr::call(StrAt(var("at"), var("substr"))),
+            Expr::If(
+                Box::new(Expr::call(InRe(var("MAX_CHAR"), RegLang::AllChar))),
+                Box::new(s("y")),
+                Box::new(s("n")),
+            ),
+        ));
+        code = format!("{}{}", rust("collide", &parameters, &body).unwrap(), code);
+        code.push_str("    println!(\"{:?}\", collide(\"abc\", 1, \"x\"));\n}\n");
+        expected.push_str("\"by\"\n");
+
+        let dir = std::env::temp_dir().join(format!("theremin-codegen-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.rs"), code).unwrap();
+        let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+        let compiled = std::process::Command::new(rustc)
+            .args(["--edition", "2021", "-A", "warnings", "-o"])
+            .arg(dir.join("main"))
+            .arg(dir.join("main.rs"))
+            .output()
+            .expect("rustc");
+        assert!(
+            compiled.status.success(),
+            "{}",
+            String::from_utf8_lossy(&compiled.stderr)
+        );
+        let output = std::process::Command::new(dir.join("main"))
+            .output()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+    }
+
+    #[test]
+    fn export_to_python_and_sql() {
+        let name = || Expr::Var("name".into());
+        let program = Expr::call(Func::Append(
+            Expr::call(Func::StrAt(name(), Expr::ConstInt(0))),
+            Expr::ConstStr("'s \"".into()),
+        ));
+        let parameters = [("name".to_string(), Sort::Identifier("String".into()))];
+        assert_eq!(
+            python(&parameters, &program).unwrap(),
+            r#"((lambda s, i: s[i:i + 1] if i >= 0 else "")(name, 0) + "'s \"")"#
+        );
+        assert_eq!(
+            sql(&parameters, &program).unwrap(),
+            r#"((CASE WHEN 0 >= 0 AND 1 > 0 THEN SUBSTRING("name" FROM 0 + 1 FOR 1) ELSE '' END) || '''s "')"#
+        );
+
+        // regular expressions have no counterpart in either
+        let re = RegLang::RCall(Box::new(RegFun::FromStr(name())));
+        assert!(sql(&parameters, &Expr::call(Func::InRe(name(), re))).is_err());
+    }
+}
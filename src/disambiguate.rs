@@ -0,0 +1,154 @@
+use crate::bank::Bank;
+use crate::language::{Eval, Expr};
+use crate::slia::{SLIALang, Weights};
+use crate::{get_term, simplify, EvalCostFn, Spec};
+use egg::{CostFunction, EGraph, Extractor, Id, Language, RecExpr};
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// The cost of [`EvalCostFn`]
+type Cost = (usize, usize, usize);
+
+/// A class to build from a node other than its best, if any
+type Deviation = Option<(Id, SLIALang)>;
+
+/// Up to `k` distinct complete programs for `root`, simplified and cheapest
+/// first: the best one, and those that differ from it in a single class,
+/// either in the node chosen or in the component of `bank` filling it.
+pub fn top_k(
+    egraph: &EGraph<SLIALang, Spec>,
+    fills: &HashMap<Id, Expr>,
+    bank: &Bank,
+    weights: &Weights,
+    root: Id,
+    k: usize,
+) -> Vec<Expr> {
+    let root = egraph.find(root);
+    let extractor = Extractor::new(egraph, EvalCostFn::new(egraph, fills, weights));
+    let (_, best) = extractor.find_best(root);
+    let mut deviations: Vec<(Deviation, Cow<HashMap<Id, Expr>>)> =
+        vec![(None, Cow::Borrowed(fills))];
+    for class in egraph.lookup_expr_ids(&best).unwrap_or_default() {
+        for node in &egraph[class].nodes {
+            deviations.push((Some((class, node.clone())), Cow::Borrowed(fills)));
+            let SLIALang::Hole(sort, spec) = node else {
+                continue;
+            };
+            let Spec::Examples(ios) = &egraph[*spec].data else {
+                continue;
+            };
+            for fill in bank.fills(&sort.to_string(), ios) {
+                if fills.get(&class) != Some(fill) {
+                    let mut other = fills.clone();
+                    other.insert(class, fill.clone());
+                    deviations.push((Some((class, node.clone())), Cow::Owned(other)));
+                }
+            }
+        }
+    }
+
+    let mut candidates: Vec<(Cost, Expr)> = Vec::new();
+    for (mut deviation, fills) in deviations {
+        let mut cost_function = EvalCostFn::new(egraph, &fills, weights);
+        let mut program = RecExpr::default();
+        let (_, cost) = build(
+            &extractor,
+            &mut cost_function,
+            root,
+            &mut deviation,
+            &mut program,
+        );
+        let (unfillable, holes, _) = cost;
+        if (unfillable, holes) != (0, 0) {
+            continue;
+        }
+        if let Ok(e) = get_term(egraph, &fills, &program) {
+            // deviations often differ only in ways simplification removes
+            let e = simplify::simplify(&e);
+            if candidates.iter().all(|(_, other)| *other != e) {
+                candidates.push((cost, e));
+            }
+        }
+    }
+    candidates.sort_by_key(|(cost, _)| *cost);
+    candidates.into_iter().take(k).map(|(_, e)| e).collect()
+}
+
+/// Adds the best program for `class` to `program`, except for the first
+/// class of `deviation`, which takes its node instead
+fn build(
+    extractor: &Extractor<EvalCostFn, SLIALang, Spec>,
+    cost_function: &mut EvalCostFn,
+    class: Id,
+    deviation: &mut Deviation,
+    program: &mut RecExpr<SLIALang>,
+) -> (Id, Cost) {
+    let node = match deviation.take_if(|(c, _)| *c == class) {
+        Some((_, node)) => node,
+        None => extractor.find_best_node(class).clone(),
+    };
+    let mut ids = HashMap::new();
+    let mut costs = HashMap::new();
+    for child in node.children() {
+        let (id, cost) = build(extractor, cost_function, *child, deviation, program);
+        ids.insert(*child, id);
+        costs.insert(*child, cost);
+    }
+    let cost = cost_function.cost(&node, |child| costs[&child]);
+    (program.add(node.map_children(|child| ids[&child])), cost)
+}
+
+/// The input of `pool` that splits `candidates` into the most groups by
+/// their output, and the number of groups, or `None` if they agree on every
+/// input. Among equal splits, the one with the smallest largest group wins,
+/// then the earliest.
+pub fn most_informative(
+    candidates: &[Expr],
+    parameter: &str,
+    pool: &[Expr],
+) -> Option<(Expr, usize)> {
+    let mut best: Option<(Expr, (usize, Reverse<usize>))> = None;
+    for input in pool {
+        let env = HashMap::from([(parameter.to_owned(), input.clone())]);
+        let mut groups: HashMap<Option<Expr>, usize> = HashMap::new();
+        for candidate in candidates {
+            // failing on an input sets a candidate apart as well
+            let output = candidate.clone().eval(&env).ok();
+            *groups.entry(output).or_default() += 1;
+        }
+        let largest = groups.values().copied().max().unwrap_or(0);
+        let split = (groups.len(), Reverse(largest));
+        if groups.len() > 1 && best.as_ref().is_none_or(|(_, best)| split > *best) {
+            best = Some((input.clone(), split));
+        }
+    }
+    best.map(|(input, (count, _))| (input, count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::Func;
+
+    #[test]
+    fn ask_where_candidates_disagree() {
+        let name = || Expr::Var("name".into());
+        let s = |s: &str| Expr::ConstStr(s.into());
+        // the first word, and the first six characters
+        let first_word = Expr::call(Func::SubStr(
+            name(),
+            Expr::ConstInt(0),
+            Expr::call(Func::Index(name(), s(" "), Expr::ConstInt(0))),
+        ));
+        let first_six = Expr::call(Func::SubStr(name(), Expr::ConstInt(0), Expr::ConstInt(6)));
+        let candidates = [first_word, first_six];
+
+        let pool = [s("Andrew Cencici"), s("Jan Kotas")];
+        assert_eq!(
+            most_informative(&candidates, "name", &pool),
+            Some((s("Jan Kotas"), 2))
+        );
+        assert_eq!(most_informative(&candidates, "name", &pool[..1]), None);
+    }
+}
@@ -1,69 +1,716 @@
-use crate::sygus::{BFTerm, GTerm, Grammar, Term};
+use crate::arena::{Node, TermArena, TermId};
+use crate::language::{Eval, Expr};
+use crate::slia::Op;
+use crate::sygus::{operator, BFTerm, GTerm, Grammar, Sort, Term};
 use itertools::Itertools;
-use std::collections::HashMap;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+use std::{iter, mem, vec};
 
-pub fn bottom_up(grammar: &Grammar, depth: usize) -> HashMap<String, Vec<Term>> {
-    let mut bank: HashMap<(String, usize), Vec<Term>> = HashMap::new();
-    for d in 0..depth {
-        for (name, _, rhs) in &grammar.rules[1..] {
-            for term in new_terms(rhs, d, &bank) {
-                bank.entry((name.to_owned(), d)).or_default().push(term);
+/// How far to enumerate a grammar
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    /// Terms of depth up to and including the bound
+    Depth(usize),
+    /// Terms of size up to and including the bound. Each node counts as the
+    /// weight of its production, so without weights the size of a term is
+    /// its number of nodes.
+    Size(usize),
+}
+
+/// Enumerates the terms of a grammar lazily, one nonterminal at a time and
+/// level by level, yielding each term with its nonterminal and sort.
+///
+/// Levels are depths or sizes, depending on the [`Bound`]. Within a level,
+/// nonterminals come in grammar order, and productions cheapest first. The
+/// order, and so the bank, is the same from run to run.
+///
+/// The nonterminals of a level only read lower levels, so a whole level is
+/// built at once, in parallel over nonterminals, productions and arguments,
+/// and then handed out in order.
+///
+/// Terms are hash-consed in a [`TermArena`], sharing their subterms, and
+/// every term is kept to build the next levels. With a memory cap, the
+/// enumerator stops before building a product that would exceed it, rather
+/// than running out of memory, and likewise with a deadline, once it has
+/// passed; [`Enumerator::truncated`] tells either apart from the bound.
+///
+/// Given example inputs, each term also comes with its outputs on them. Those
+/// of banked terms are kept, so that a new term is evaluated by applying its
+/// operator to its children's outputs rather than by walking the whole tree.
+pub struct Enumerator<'g> {
+    grammar: &'g Grammar,
+    bound: Bound,
+    arena: TermArena<'g>,
+    bank: Bank<'g>,
+    /// Terms already banked for each nonterminal, which later depths rebuild
+    banked: HashSet<(&'g str, TermId)>,
+    level: usize,
+    /// The next nonterminal to build, as an index into `grammar.rules`
+    rule: usize,
+    /// The terms of each nonterminal at the current level, built together
+    /// and taken one nonterminal at a time; `None` past the memory cap
+    level_nodes: Vec<Option<Vec<Node<'g>>>>,
+    pending: Pending<'g>,
+    /// The nonterminal, sort and level of the terms being yielded
+    current: Option<(&'g str, &'g Sort, usize)>,
+    /// Bytes left under the memory cap, if there is one
+    budget: Option<usize>,
+    /// When to stop building terms, if ever
+    deadline: Option<Instant>,
+    truncated: bool,
+    /// The parameter of the program and its example inputs
+    inputs: Option<(String, Vec<Expr>)>,
+    /// Outputs of banked terms, by id; `None` for terms that have no value,
+    /// like regular expressions
+    outputs: Vec<Option<Outputs>>,
+}
+
+/// The output of a term on each example input, `None` where it fails
+type Outputs = Box<[Option<Expr>]>;
+
+/// A term from an [`Enumerator`], by its id in [`Enumerator::arena`], so
+/// that consumers only rebuild the terms they keep
+#[derive(Clone, Debug)]
+pub struct Enumerated<'g> {
+    pub nonterminal: &'g str,
+    pub sort: &'g Sort,
+    pub id: TermId,
+    /// The outputs on the example inputs, if there are examples and the term
+    /// evaluates on all of them
+    pub outputs: Option<Vec<Expr>>,
+}
+
+impl<'g> Enumerator<'g> {
+    pub fn new(grammar: &'g Grammar, bound: Bound) -> Self {
+        Enumerator {
+            grammar,
+            bound,
+            arena: TermArena::new(),
+            bank: HashMap::new(),
+            banked: HashSet::new(),
+            level: match bound {
+                Bound::Depth(_) => 0,
+                Bound::Size(_) => 1,
+            },
+            // the first rule is the start symbol, which only renames another
+            rule: 1,
+            level_nodes: Vec::new(),
+            pending: Pending::Last(Vec::new().into_iter()),
+            current: None,
+            budget: None,
+            deadline: None,
+            truncated: false,
+            inputs: None,
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Evaluates every term on `inputs`, given to `parameter`
+    pub fn with_inputs(mut self, parameter: &str, inputs: Vec<Expr>) -> Self {
+        self.inputs = Some((parameter.to_owned(), inputs));
+        self
+    }
+
+    /// Caps the estimated memory held by enumerated terms at `bytes`
+    pub fn with_max_memory(mut self, bytes: usize) -> Self {
+        self.budget = Some(bytes);
+        self
+    }
+
+    /// Stops building terms at `deadline`, even in the middle of a level
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Whether enumeration stopped at the memory cap or the deadline instead
+    /// of the bound
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// The terms enumerated so far
+    pub fn arena(&self) -> &TermArena<'g> {
+        &self.arena
+    }
+
+    /// The level of the terms being yielded, once there are any
+    pub fn level(&self) -> Option<usize> {
+        self.current.map(|(_, _, level)| level)
+    }
+
+    fn in_bound(&self, level: usize) -> bool {
+        match self.bound {
+            Bound::Depth(depth) => level < depth,
+            Bound::Size(size) => level <= size,
+        }
+    }
+
+    /// Builds the terms of every nonterminal at the current level
+    fn build_level(&mut self) {
+        let rules = &self.grammar.rules[1..];
+        // literal arguments, as in `(+ ntInt 1)`, are fixed children
+        for (_, _, rhs) in rules {
+            for g_term in rhs {
+                if let GTerm::BFTerm(BFTerm::Application(_, holes)) = g_term {
+                    for hole in holes {
+                        if let BFTerm::Literal(lit) = hole {
+                            // also kept when first seen as a term that was not
+                            let (id, _) = self.arena.add(Node::Literal(lit));
+                            self.evaluate(id, None, true);
+                        }
+                    }
+                }
             }
         }
+        // the memory cap is charged in grammar order, so that it cuts a
+        // level at the same nonterminal from run to run
+        let mut plans = Vec::new();
+        for (name, _, rhs) in rules {
+            if expired(self.deadline) {
+                plans.resize_with(rules.len(), || None);
+                break;
+            }
+            let weights = (0..rhs.len()).map(|i| self.grammar.weight(name, i));
+            let plan = match self.bound {
+                Bound::Depth(_) => {
+                    let mut productions: Vec<(&GTerm, usize)> = rhs.iter().zip(weights).collect();
+                    productions.sort_by_key(|(_, weight)| *weight);
+                    let g_terms = productions.into_iter().map(|(g_term, _)| g_term);
+                    new_terms(
+                        g_terms,
+                        self.level,
+                        &self.bank,
+                        &self.arena,
+                        &mut self.budget,
+                    )
+                }
+                Bound::Size(_) => sized_terms(
+                    rhs.iter().zip(weights),
+                    self.level,
+                    &self.bank,
+                    &self.arena,
+                    &mut self.budget,
+                ),
+            };
+            let full = plan.is_none();
+            plans.push(plan);
+            if full {
+                plans.resize_with(rules.len(), || None);
+                break;
+            }
+        }
+        let deadline = self.deadline;
+        let nodes = plans.into_par_iter().map(|plan| build(plan?, deadline));
+        // the start symbol is not built
+        self.level_nodes = iter::once(None).chain(nodes.collect::<Vec<_>>()).collect();
     }
-    let mut terms: HashMap<String, Vec<Term>> = HashMap::new();
-    for d in 0..depth {
-        for (name, sort, _) in &grammar.rules[1..] {
-            if let Some(ts) = bank.get_mut(&(name.to_owned(), d)) {
-                terms
-                    .entry(sort.to_string())
-                    .and_modify(|e| e.append(ts))
-                    .or_insert(ts.to_vec());
+
+    /// Hands out the terms of the next nonterminal at the current level,
+    /// building the level first if it is new
+    fn advance(&mut self) -> Option<()> {
+        if self.truncated || self.grammar.rules.len() < 2 || !self.in_bound(self.level) {
+            return None;
+        }
+        if self.level_nodes.is_empty() {
+            self.build_level();
+        }
+        let (name, sort, _) = &self.grammar.rules[self.rule];
+        let Some(nodes) = self.level_nodes[self.rule].take() else {
+            self.truncated = true;
+            return None;
+        };
+        let mut ids = Vec::new();
+        for node in nodes {
+            let (id, _) = self.arena.add(node);
+            if self.banked.insert((name, id)) {
+                ids.push(id);
+            }
+        }
+        // nothing is built from the last level, so it is handed out instead
+        self.pending = if !self.in_bound(self.level + 1) {
+            Pending::Last(ids.into_iter())
+        } else {
+            // the outputs of banked terms are kept as well
+            if let (Some(budget), Some((_, inputs))) = (&mut self.budget, &self.inputs) {
+                let bytes = ids.len() * inputs.len() * mem::size_of::<Option<Expr>>();
+                let Some(left) = budget.checked_sub(bytes) else {
+                    self.truncated = true;
+                    return None;
+                };
+                *budget = left;
+            }
+            self.bank.insert((name, self.level), ids);
+            Pending::Banked((name, self.level), 0)
+        };
+        self.current = Some((name, sort, self.level));
+        self.rule += 1;
+        if self.rule == self.grammar.rules.len() {
+            self.level_nodes.clear();
+            self.rule = 1;
+            self.level += 1;
+        }
+        Some(())
+    }
+
+    /// The outputs of term `id` of `sort`, kept if `keep` for its parents.
+    /// Literal arguments have no sort of their own, and are never regular
+    /// expressions.
+    fn evaluate(&mut self, id: TermId, sort: Option<&Sort>, keep: bool) -> Option<Vec<Expr>> {
+        let (parameter, inputs) = self.inputs.as_ref()?;
+        if matches!(sort, Some(Sort::Identifier(sort)) if sort == "RegLan") {
+            return None;
+        }
+        let outputs = match self.outputs.get(id.index()) {
+            Some(Some(outputs)) => outputs.clone(),
+            _ => {
+                let children: Option<Vec<&[Option<Expr>]>> = match self.arena.node(id) {
+                    Node::Application(_, args) => args
+                        .iter()
+                        .map(|a| self.outputs.get(a.index())?.as_deref())
+                        .collect(),
+                    _ => None,
+                };
+                // the operator is looked up once, and applied to each
+                // example's argument values
+                let op = match self.arena.node(id) {
+                    Node::Application(name, args) => operator(name, args.len()),
+                    _ => None,
+                };
+                let outputs: Outputs = match (self.arena.node(id), children, op) {
+                    (Node::Identifier(x), _, _) if x == parameter => {
+                        inputs.iter().cloned().map(Some).collect()
+                    }
+                    (Node::Application(name, _), Some(children), _) if *name == "ite" => {
+                        (0..inputs.len()).map(|i| ite(&children, i)).collect()
+                    }
+                    (Node::Application(..), Some(children), Some(op)) => {
+                        (0..inputs.len()).map(|i| apply(op, &children, i)).collect()
+                    }
+                    // constants, and terms over children without outputs
+                    _ => {
+                        let expr: Option<Expr> = (&self.arena.term(id)).try_into().ok();
+                        inputs
+                            .iter()
+                            .map(|input| {
+                                let env = HashMap::from([(parameter.to_owned(), input.clone())]);
+                                expr.clone()?.eval(&env).ok()
+                            })
+                            .collect()
+                    }
+                };
+                if keep {
+                    if self.outputs.len() <= id.index() {
+                        self.outputs.resize(id.index() + 1, None);
+                    }
+                    self.outputs[id.index()] = Some(outputs.clone());
+                }
+                outputs
             }
+        };
+        outputs.into_vec().into_iter().collect()
+    }
+}
+
+/// The output of `(ite c t e)` on example `i`, from those of its arguments
+fn ite(args: &[&[Option<Expr>]], i: usize) -> Option<Expr> {
+    let [c, t, e] = args else {
+        return None;
+    };
+    match c[i] {
+        Some(Expr::ConstBool(true)) => t[i].clone(),
+        Some(Expr::ConstBool(false)) => e[i].clone(),
+        _ => None,
+    }
+}
+
+/// Applies `op` to the outputs of its arguments on example `i`
+fn apply(op: Op, args: &[&[Option<Expr>]], i: usize) -> Option<Expr> {
+    let args: Option<Vec<Expr>> = args.iter().map(|arg| arg[i].clone()).collect();
+    Expr::call(op.call(&args?)?).eval(&HashMap::new()).ok()
+}
+
+impl<'g> Iterator for Enumerator<'g> {
+    type Item = Enumerated<'g>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (id, keep) = match &mut self.pending {
+                Pending::Banked(key, i) => {
+                    *i += 1;
+                    (self.bank[key].get(*i - 1).copied(), true)
+                }
+                Pending::Last(ids) => (ids.next(), false),
+            };
+            if let Some(id) = id {
+                let (nonterminal, sort, _) = self.current?;
+                return Some(Enumerated {
+                    nonterminal,
+                    sort,
+                    id,
+                    outputs: self.evaluate(id, Some(sort), keep),
+                });
+            }
+            self.advance()?;
         }
     }
+}
+
+/// The terms of the nonterminal being yielded
+enum Pending<'g> {
+    /// Terms kept in the bank, and the index of the next one
+    Banked((&'g str, usize), usize),
+    /// Terms of the last level, which are not kept
+    Last(vec::IntoIter<TermId>),
+}
+
+/// Enumerates the terms of every nonterminal up to `depth`, shallowest first,
+/// and within a depth cheapest production first.
+pub fn bottom_up(grammar: &Grammar, depth: usize) -> HashMap<String, Vec<Term>> {
+    by_sort(Enumerator::new(grammar, Bound::Depth(depth)))
+}
+
+/// Enumerates the terms of every nonterminal of size at most `max_size`,
+/// smallest first.
+///
+/// Unlike depth, size grows the bank gradually, which reaches small but deep
+/// programs long before a depth bound would.
+pub fn by_size(grammar: &Grammar, max_size: usize) -> HashMap<String, Vec<Term>> {
+    by_sort(Enumerator::new(grammar, Bound::Size(max_size)))
+}
+
+/// Groups enumerated terms by sort, in enumeration order
+fn by_sort(mut enumerator: Enumerator) -> HashMap<String, Vec<Term>> {
+    let mut terms: HashMap<String, Vec<Term>> = HashMap::new();
+    while let Some(Enumerated { sort, id, .. }) = enumerator.next() {
+        let term = enumerator.arena().term(id);
+        terms.entry(sort.to_string()).or_default().push(term);
+    }
     terms
 }
 
-fn new_terms(
-    g_terms: &Vec<GTerm>,
+type Bank<'g> = HashMap<(&'g str, usize), Vec<TermId>>;
+
+/// Terms of one production to build: a leaf, or the applications of an
+/// operator to one term from each fill
+enum Product<'g> {
+    Leaf(Node<'g>),
+    Applications(&'g str, Vec<Vec<TermId>>),
+}
+
+/// The terms of `products`, in order, each product built in parallel, or
+/// `None` if `deadline` passes first
+fn build(products: Vec<Product>, deadline: Option<Instant>) -> Option<Vec<Node>> {
+    let nodes: Vec<Vec<Node>> = products
+        .into_par_iter()
+        .map(|product| match product {
+            Product::Leaf(node) => Some(vec![node]),
+            Product::Applications(name, fills) => applications(name, &fills, deadline),
+        })
+        .collect::<Option<_>>()?;
+    Some(nodes.into_iter().flatten().collect())
+}
+
+fn expired(deadline: Option<Instant>) -> bool {
+    deadline.is_some_and(|deadline| Instant::now() > deadline)
+}
+
+fn new_terms<'g>(
+    g_terms: impl Iterator<Item = &'g GTerm>,
     depth: usize,
-    bank: &HashMap<(String, usize), Vec<Term>>,
-) -> Vec<Term> {
-    let mut terms: Vec<Term> = Vec::new();
+    bank: &Bank<'g>,
+    arena: &TermArena<'g>,
+    budget: &mut Option<usize>,
+) -> Option<Vec<Product<'g>>> {
+    let mut products = Vec::new();
     for g_term in g_terms {
         match g_term {
             GTerm::BFTerm(BFTerm::Application(name, holes)) if depth > 0 => {
-                let fills = holes.iter().map(|hole| fill(hole, depth, bank).into_iter());
-                for fill in fills.multi_cartesian_product() {
-                    terms.push(Term::Application(name.to_owned(), fill));
-                }
+                let fills: Vec<Vec<TermId>> = holes
+                    .iter()
+                    .map(|hole| fill(hole, depth, bank, arena))
+                    .collect();
+                charge(&fills, budget)?;
+                products.push(Product::Applications(name, fills));
             }
             GTerm::BFTerm(BFTerm::Identifier(name)) if depth == 0 => {
-                terms.push(Term::Identifier(name.to_owned()));
+                products.push(Product::Leaf(Node::Identifier(name)));
             }
             GTerm::BFTerm(BFTerm::Literal(lit)) if depth == 0 => {
-                terms.push(Term::Literal(lit.to_owned()));
+                products.push(Product::Leaf(Node::Literal(lit)));
             }
             _ => {} // do nothing
         }
     }
-    terms
+    Some(products)
 }
 
-fn fill(bf_term: &BFTerm, depth: usize, bank: &HashMap<(String, usize), Vec<Term>>) -> Vec<Term> {
-    let mut terms: Vec<Term> = Vec::new();
+fn fill<'g>(bf_term: &'g BFTerm, depth: usize, bank: &Bank, arena: &TermArena<'g>) -> Vec<TermId> {
+    let mut terms: Vec<TermId> = Vec::new();
     match bf_term {
         BFTerm::Identifier(name) => {
             for d in 0..depth {
-                if let Some(filler) = bank.get(&(name.to_owned(), d)) {
-                    for term in filler {
-                        terms.push(term.to_owned());
-                    }
+                if let Some(filler) = bank.get(&(name.as_str(), d)) {
+                    terms.extend(filler);
                 }
             }
         }
-        _ => unimplemented!("Cannot fill a non-identifier"),
+        BFTerm::Literal(lit) => terms.extend(arena.get(&Node::Literal(lit))),
+        BFTerm::Application(..) => unimplemented!("Cannot fill with an application"),
     }
     terms
 }
+
+fn sized_terms<'g>(
+    g_terms: impl Iterator<Item = (&'g GTerm, usize)>,
+    size: usize,
+    bank: &Bank<'g>,
+    arena: &TermArena<'g>,
+    budget: &mut Option<usize>,
+) -> Option<Vec<Product<'g>>> {
+    let mut products = Vec::new();
+    for (g_term, weight) in g_terms {
+        match g_term {
+            GTerm::BFTerm(BFTerm::Application(name, holes)) if size >= weight + holes.len() => {
+                for sizes in compositions(size - weight, holes.len()) {
+                    let fills: Option<Vec<Vec<TermId>>> = holes
+                        .iter()
+                        .zip(sizes)
+                        .map(|(hole, n)| match hole {
+                            BFTerm::Identifier(nt) => bank.get(&(nt.as_str(), n)).cloned(),
+                            // a literal is a single node
+                            BFTerm::Literal(lit) if n == 1 => {
+                                Some(arena.get(&Node::Literal(lit)).into_iter().collect())
+                            }
+                            BFTerm::Literal(_) => None,
+                            BFTerm::Application(..) => {
+                                unimplemented!("Cannot fill with an application")
+                            }
+                        })
+                        .collect();
+                    if let Some(fills) = fills {
+                        charge(&fills, budget)?;
+                        products.push(Product::Applications(name, fills));
+                    }
+                }
+            }
+            GTerm::BFTerm(BFTerm::Identifier(name)) if size == weight => {
+                products.push(Product::Leaf(Node::Identifier(name)));
+            }
+            GTerm::BFTerm(BFTerm::Literal(lit)) if size == weight => {
+                products.push(Product::Leaf(Node::Literal(lit)));
+            }
+            _ => {} // do nothing
+        }
+    }
+    Some(products)
+}
+
+/// The ways to write `total` as an ordered sum of `parts` positive numbers
+fn compositions(total: usize, parts: usize) -> Vec<Vec<usize>> {
+    if parts == 0 {
+        return if total == 0 {
+            vec![Vec::new()]
+        } else {
+            Vec::new()
+        };
+    }
+    (1..=total.saturating_sub(parts - 1))
+        .flat_map(|first| {
+            compositions(total - first, parts - 1)
+                .into_iter()
+                .map(move |rest| iter::once(first).chain(rest).collect())
+        })
+        .collect()
+}
+
+/// Takes the memory held by the applications of an operator to one term
+/// from each of `fills` out of `budget`, or returns `None` if they would not
+/// fit in it
+fn charge(fills: &[Vec<TermId>], budget: &mut Option<usize>) -> Option<()> {
+    if let Some(budget) = budget {
+        // a node is stored in the arena and its index, and banked by id
+        let node = mem::size_of::<Node>() + fills.len() * mem::size_of::<TermId>();
+        let count = fills.iter().fold(1, |n, fill| fill.len().saturating_mul(n));
+        let bytes = count.saturating_mul(2 * node + 3 * mem::size_of::<TermId>());
+        *budget = budget.checked_sub(bytes)?;
+    }
+    Some(())
+}
+
+/// Every application of `name` to one term from each of `fills`, or `None`
+/// if `deadline` passes first.
+///
+/// The product is split on its first argument to build it in parallel, in the
+/// same order as a sequential `multi_cartesian_product`.
+fn applications<'g>(
+    name: &'g str,
+    fills: &[Vec<TermId>],
+    deadline: Option<Instant>,
+) -> Option<Vec<Node<'g>>> {
+    let Some((first, rest)) = fills.split_first() else {
+        return Some(Vec::new());
+    };
+    let tails: Vec<Vec<TermId>> = if rest.is_empty() {
+        vec![Vec::new()]
+    } else {
+        rest.iter()
+            .map(|f| f.iter().copied())
+            .multi_cartesian_product()
+            .collect()
+    };
+    let nodes: Vec<Vec<Node>> = first
+        .par_iter()
+        .map(|head| {
+            if expired(deadline) {
+                return None;
+            }
+            let nodes = tails.iter().map(|tail| {
+                let args = iter::once(*head).chain(tail.iter().copied());
+                Node::Application(name, args.collect())
+            });
+            Some(nodes.collect())
+        })
+        .collect::<Option<_>>()?;
+    Some(nodes.into_iter().flatten().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sygus;
+
+    fn size(term: &Term) -> usize {
+        match term {
+            Term::Application(_, args) => 1 + args.iter().map(size).sum::<usize>(),
+            _ => 1,
+        }
+    }
+
+    #[test]
+    fn enumerate_by_size() {
+        let conjecture =
+            sygus::parse_file(include_str!("../assets/benchmarks/bikes.sl")).expect("parse");
+        let grammar = &conjecture.functions_to_synthesize[0].grammar;
+        let bank = by_size(grammar, 4);
+
+        let ints = &bank["Int"];
+        assert!(ints.windows(2).all(|w| size(&w[0]) <= size(&w[1])));
+        assert_eq!(ints.iter().map(size).max(), Some(4));
+        let strings: Vec<String> = bank["String"].iter().map(|t| t.to_string()).collect();
+        assert!(strings.contains(&"(str.at name (str.len name))".to_string()));
+    }
+
+    #[test]
+    fn literal_arguments() {
+        let conjecture = sygus::parse_file(
+            "(synth-fun f ((x Int)) Int ((Start Int) (I Int)) \
+             ((Start Int (I)) (I Int (x (+ I 1)))))",
+        )
+        .expect("parse");
+        let grammar = &conjecture.functions_to_synthesize[0].grammar;
+        let mut enumerator =
+            Enumerator::new(grammar, Bound::Size(5)).with_inputs("x", vec![Expr::ConstInt(1)]);
+        let sized: Vec<Enumerated> = enumerator.by_ref().collect();
+        let sized: Vec<String> = sized
+            .iter()
+            .map(|e| format!("{} {:?}", enumerator.arena().term(e.id), e.outputs))
+            .collect();
+        assert_eq!(
+            sized,
+            [
+                "x Some([ConstInt(1)])",
+                "(+ x 1) Some([ConstInt(2)])",
+                "(+ (+ x 1) 1) Some([ConstInt(3)])"
+            ]
+        );
+        let deep: Vec<String> = bottom_up(grammar, 3)["Int"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        assert_eq!(deep, ["x", "(+ x 1)", "(+ (+ x 1) 1)"]);
+    }
+
+    #[test]
+    fn literal_first_seen_as_regular_expression() {
+        let conjecture = sygus::parse_file(
+            "(synth-fun f ((x String)) Bool ((Start Bool) (R RegLan) (S String)) \
+             ((Start Bool ((str.in_re S R))) (R RegLan ((str.to_re \"a\"))) \
+             (S String (x (str.++ S \"a\")))))",
+        )
+        .expect("parse");
+        let grammar = &conjecture.functions_to_synthesize[0].grammar;
+        let mut enumerator = Enumerator::new(grammar, Bound::Depth(2))
+            .with_inputs("x", vec![Expr::ConstStr("b".into())]);
+        let outputs: Vec<String> = enumerator
+            .by_ref()
+            .filter(|e| e.nonterminal == "S")
+            .map(|e| format!("{:?}", e.outputs))
+            .collect();
+        assert_eq!(
+            outputs,
+            ["Some([ConstStr(\"b\")])", "Some([ConstStr(\"ba\")])"]
+        );
+        // the literal's outputs are kept for its parents, although
+        // `(str.to_re "a")` was built first
+        let a = sygus::Literal::String("a".into());
+        let a = enumerator.arena().get(&Node::Literal(&a)).expect("literal");
+        assert!(matches!(enumerator.outputs.get(a.index()), Some(Some(_))));
+    }
+
+    #[test]
+    fn stream_under_memory_cap() {
+        let conjecture =
+            sygus::parse_file(include_str!("../assets/benchmarks/bikes.sl")).expect("parse");
+        let grammar = &conjecture.functions_to_synthesize[0].grammar;
+
+        let mut enumerator = Enumerator::new(grammar, Bound::Depth(3));
+        let first: Vec<Enumerated> = enumerator.by_ref().take(3).collect();
+        let first: Vec<String> = first
+            .iter()
+            .map(|e| format!("{} {}", e.nonterminal, enumerator.arena().term(e.id)))
+            .collect();
+        assert_eq!(first, ["ntString name", "ntString \" \"", "ntInt 0"]);
+
+        let mut capped = Enumerator::new(grammar, Bound::Depth(3)).with_max_memory(1 << 20);
+        let count = capped.by_ref().count();
+        assert!(capped.truncated());
+        let uncapped = Enumerator::new(grammar, Bound::Depth(2));
+        assert!(count >= uncapped.count());
+
+        // a deadline stops the enumerator in the middle of a level
+        let mut late = Enumerator::new(grammar, Bound::Depth(4)).with_deadline(Instant::now());
+        assert_eq!(late.by_ref().count(), 0);
+        assert!(late.truncated());
+    }
+
+    #[test]
+    fn outputs_match_whole_term_evaluation() {
+        let conjecture =
+            sygus::parse_file(include_str!("../assets/benchmarks/bikes.sl")).expect("parse");
+        let grammar = &conjecture.functions_to_synthesize[0].grammar;
+        let inputs = vec![
+            Expr::ConstStr("Ducati100".into()),
+            Expr::ConstStr("".into()),
+        ];
+
+        let mut enumerator =
+            Enumerator::new(grammar, Bound::Size(5)).with_inputs("name", inputs.clone());
+        let mut evaluated = 0;
+        while let Some(Enumerated { id, outputs, .. }) = enumerator.next() {
+            let expr: Expr = (&enumerator.arena().term(id)).try_into().expect("expr");
+            let expected: Option<Vec<Expr>> = inputs
+                .iter()
+                .map(|i| {
+                    let env = HashMap::from([("name".to_string(), i.clone())]);
+                    expr.clone().eval(&env).ok()
+                })
+                .collect();
+            assert_eq!(outputs, expected, "{:?}", expr);
+            evaluated += outputs.is_some() as usize;
+        }
+        assert!(evaluated > 100);
+    }
+}
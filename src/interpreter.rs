@@ -1,5 +1,5 @@
+use crate::automaton::Re;
 pub use crate::language::{Eval, Expr, Func, RegFun, RegLang, Term, Translate};
-use regex::Regex;
 use std::collections::HashMap;
 
 impl Eval for Expr {
@@ -67,12 +67,40 @@ impl Eval for Func {
                 }
             }
 
+            Self::Lt(arg1, arg2) => {
+                let arg1_evaled = arg1.eval(env)?;
+                let arg2_evaled = arg2.eval(env)?;
+
+                match (&arg1_evaled, &arg2_evaled) {
+                    (Expr::ConstInt(a1), Expr::ConstInt(a2)) => Ok(Expr::ConstBool(a1 < a2)),
+                    _ => Err(format!(
+                        "Lt: invalid argument: arg1 = {:?}, arg2 = {:?}",
+                        arg1_evaled, arg2_evaled
+                    )),
+                }
+            }
+
+            Self::Gt(arg1, arg2) => {
+                let arg1_evaled = arg1.eval(env)?;
+                let arg2_evaled = arg2.eval(env)?;
+
+                match (&arg1_evaled, &arg2_evaled) {
+                    (Expr::ConstInt(a1), Expr::ConstInt(a2)) => Ok(Expr::ConstBool(a1 > a2)),
+                    _ => Err(format!(
+                        "Gt: invalid argument: arg1 = {:?}, arg2 = {:?}",
+                        arg1_evaled, arg2_evaled
+                    )),
+                }
+            }
+
             Self::Eql(arg1, arg2) => {
                 let arg1_evaled = arg1.eval(env)?;
                 let arg2_evaled = arg2.eval(env)?;
 
                 match (&arg1_evaled, &arg2_evaled) {
                     (Expr::ConstInt(a1), Expr::ConstInt(a2)) => Ok(Expr::ConstBool(a1 == a2)),
+                    (Expr::ConstStr(a1), Expr::ConstStr(a2)) => Ok(Expr::ConstBool(a1 == a2)),
+                    (Expr::ConstBool(a1), Expr::ConstBool(a2)) => Ok(Expr::ConstBool(a1 == a2)),
                     _ => Err(format!(
                         "Eql: invalid argument: arg1 = {:?}, arg2 = {:?}",
                         arg1_evaled, arg2_evaled
@@ -85,7 +113,10 @@ impl Eval for Func {
                 let arg2_evaled = arg2.eval(env)?;
 
                 match (&arg1_evaled, &arg2_evaled) {
-                    (Expr::ConstInt(a1), Expr::ConstInt(a2)) => Ok(Expr::ConstInt(a1 + a2)),
+                    (Expr::ConstInt(a1), Expr::ConstInt(a2)) => a1
+                        .checked_add(*a2)
+                        .map(Expr::ConstInt)
+                        .ok_or_else(|| overflow("Add", &arg1_evaled, &arg2_evaled)),
                     _ => Err(format!(
                         "Add: invalid argument: arg1 = {:?}, arg2 = {:?}",
                         arg1_evaled, arg2_evaled
@@ -98,7 +129,10 @@ impl Eval for Func {
                 let arg2_evaled = arg2.eval(env)?;
 
                 match (&arg1_evaled, &arg2_evaled) {
-                    (Expr::ConstInt(a1), Expr::ConstInt(a2)) => Ok(Expr::ConstInt(a1 - a2)),
+                    (Expr::ConstInt(a1), Expr::ConstInt(a2)) => a1
+                        .checked_sub(*a2)
+                        .map(Expr::ConstInt)
+                        .ok_or_else(|| overflow("Min", &arg1_evaled, &arg2_evaled)),
                     _ => Err(format!(
                         "Min: invalid argument: arg1 = {:?}, arg2 = {:?}",
                         arg1_evaled, arg2_evaled
@@ -111,7 +145,10 @@ impl Eval for Func {
                 let arg2_evaled = arg2.eval(env)?;
 
                 match (&arg1_evaled, &arg2_evaled) {
-                    (Expr::ConstInt(a1), Expr::ConstInt(a2)) => Ok(Expr::ConstInt(a1 * a2)),
+                    (Expr::ConstInt(a1), Expr::ConstInt(a2)) => a1
+                        .checked_mul(*a2)
+                        .map(Expr::ConstInt)
+                        .ok_or_else(|| overflow("Mult", &arg1_evaled, &arg2_evaled)),
                     _ => Err(format!(
                         "Mult: invalid argument: arg1 = {:?}, arg2 = {:?}",
                         arg1_evaled, arg2_evaled
@@ -124,7 +161,14 @@ impl Eval for Func {
                 let arg2_evaled = arg2.eval(env)?;
 
                 match (&arg1_evaled, &arg2_evaled) {
-                    (Expr::ConstInt(a1), Expr::ConstInt(a2)) => Ok(Expr::ConstInt(a1 / a2)),
+                    (Expr::ConstInt(_), Expr::ConstInt(0)) => {
+                        Err(String::from("Div: division by zero"))
+                    }
+                    // SMT-LIB integer division is euclidean
+                    (Expr::ConstInt(a1), Expr::ConstInt(a2)) => a1
+                        .checked_div_euclid(*a2)
+                        .map(Expr::ConstInt)
+                        .ok_or_else(|| overflow("Div", &arg1_evaled, &arg2_evaled)),
                     _ => Err(format!(
                         "Div: invalid argument: arg1 = {:?}, arg2 = {:?}",
                         arg1_evaled, arg2_evaled
@@ -136,7 +180,10 @@ impl Eval for Func {
                 let arg1_evaled = arg1.eval(env)?;
 
                 match &arg1_evaled {
-                    Expr::ConstInt(a1) => Ok(Expr::ConstInt(a1.abs())),
+                    Expr::ConstInt(a1) => a1
+                        .checked_abs()
+                        .map(Expr::ConstInt)
+                        .ok_or_else(|| format!("Abs: integer overflow: arg1 = {:?}", a1)),
                     _ => Err(format!("Abs: invalid argument: arg1 = {:?}", arg1_evaled)),
                 }
             }
@@ -146,7 +193,13 @@ impl Eval for Func {
                 let arg2_evaled = arg2.eval(env)?;
 
                 match (&arg1_evaled, &arg2_evaled) {
-                    (Expr::ConstInt(a1), Expr::ConstInt(a2)) => Ok(Expr::ConstInt(a1 % a2)),
+                    (Expr::ConstInt(_), Expr::ConstInt(0)) => {
+                        Err(String::from("Mod: division by zero"))
+                    }
+                    (Expr::ConstInt(a1), Expr::ConstInt(a2)) => a1
+                        .checked_rem_euclid(*a2)
+                        .map(Expr::ConstInt)
+                        .ok_or_else(|| overflow("Mod", &arg1_evaled, &arg2_evaled)),
                     _ => Err(format!(
                         "Mod: invalid argument: arg1 = {:?}, arg2 = {:?}",
                         arg1_evaled, arg2_evaled
@@ -158,7 +211,10 @@ impl Eval for Func {
                 let arg1_evaled = arg1.eval(env)?;
 
                 match &arg1_evaled {
-                    Expr::ConstInt(a1) => Ok(Expr::ConstInt(a1 * (-1))),
+                    Expr::ConstInt(a1) => a1
+                        .checked_neg()
+                        .map(Expr::ConstInt)
+                        .ok_or_else(|| format!("NegI: integer overflow: arg1 = {:?}", a1)),
                     _ => Err(format!("NegI: invalid argument: arg1 = {:?}", arg1_evaled)),
                 }
             }
@@ -168,7 +224,7 @@ impl Eval for Func {
 
                 match &arg1_evaled {
                     Expr::ConstBool(a1) => Ok(Expr::ConstBool(!a1)),
-                    _ => Err(format!("NegI: invalid argument: arg1 = {:?}", arg1_evaled)),
+                    _ => Err(format!("NegB: invalid argument: arg1 = {:?}", arg1_evaled)),
                 }
             }
 
@@ -198,11 +254,37 @@ impl Eval for Func {
                 }
             }
 
+            Self::Xor(arg1, arg2) => {
+                let arg1_evaled = arg1.eval(env)?;
+                let arg2_evaled = arg2.eval(env)?;
+
+                match (&arg1_evaled, &arg2_evaled) {
+                    (Expr::ConstBool(a1), Expr::ConstBool(a2)) => Ok(Expr::ConstBool(a1 ^ a2)),
+                    _ => Err(format!(
+                        "Xor: invalid argument: arg1 = {:?}, arg2 = {:?}",
+                        arg1_evaled, arg2_evaled
+                    )),
+                }
+            }
+
+            Self::Implies(arg1, arg2) => {
+                let arg1_evaled = arg1.eval(env)?;
+                let arg2_evaled = arg2.eval(env)?;
+
+                match (&arg1_evaled, &arg2_evaled) {
+                    (Expr::ConstBool(a1), Expr::ConstBool(a2)) => Ok(Expr::ConstBool(!a1 || *a2)),
+                    _ => Err(format!(
+                        "Implies: invalid argument: arg1 = {:?}, arg2 = {:?}",
+                        arg1_evaled, arg2_evaled
+                    )),
+                }
+            }
+
             Self::StrLen(arg1) => {
                 let arg1_evaled = arg1.eval(env)?;
 
                 match &arg1_evaled {
-                    Expr::ConstStr(a1) => Ok(Expr::ConstInt(a1.len() as i64)),
+                    Expr::ConstStr(a1) => Ok(Expr::ConstInt(a1.chars().count() as i64)),
                     _ => Err(format!(
                         "StrLen: invalid argument: arg1 = {:?}",
                         arg1_evaled
@@ -215,11 +297,11 @@ impl Eval for Func {
                 let arg2_evaled = arg2.eval(env)?;
 
                 match (&arg1_evaled, &arg2_evaled) {
-                    (Expr::ConstStr(a1), Expr::ConstInt(a2)) => Ok(Expr::ConstStr(
-                        a1.chars().nth(*a2 as usize).map_or("".into(), |c| c.into()),
-                    )),
+                    (Expr::ConstStr(a1), Expr::ConstInt(a2)) => {
+                        Ok(Expr::ConstStr(substr(a1, *a2, 1)))
+                    }
                     _ => Err(format!(
-                        "StrLen: invalid argument: arg1 = {:?} arg2 = {:?}",
+                        "StrAt: invalid argument: arg1 = {:?} arg2 = {:?}",
                         arg1_evaled, arg2_evaled
                     )),
                 }
@@ -232,28 +314,7 @@ impl Eval for Func {
 
                 match (&arg1_evaled, &arg2_evaled, &arg3_evaled) {
                     (Expr::ConstStr(a1), Expr::ConstInt(a2), Expr::ConstInt(a3)) => {
-                        if (a3 > &0) && (a2 < &(a1.len() as i64)) && (a2 >= &0) {
-                            // if either the number to take is 0 or less or the index is out of bounds return mt string
-                            if (a2 + a3 - 1) < (a1.len() as i64) {
-                                // if index plus number to take is greater than length of string decide on number to take
-                                Ok(Expr::ConstStr(
-                                    a1.chars()
-                                        .skip(*a2 as usize)
-                                        .take((a3 - a2) as usize)
-                                        .collect::<String>(),
-                                ))
-                            } else {
-                                let j = (a1.len() as i64) - a2;
-                                Ok(Expr::ConstStr(
-                                    a1.chars()
-                                        .skip(*a2 as usize)
-                                        .take((j) as usize)
-                                        .collect::<String>(),
-                                ))
-                            }
-                        } else {
-                            Ok(Expr::ConstStr("".to_string()))
-                        }
+                        Ok(Expr::ConstStr(substr(a1, *a2, *a3)))
                     }
                     _ => Err(format!(
                         "SubStr: invalid argument: arg1 = {:?} arg2 = {:?}, arg3 = {:?}",
@@ -267,16 +328,9 @@ impl Eval for Func {
                 let arg2_evaled = arg2.eval(env)?;
 
                 match (&arg1_evaled, &arg2_evaled) {
+                    // (str.prefixof s t) is true iff s is a prefix of t
                     (Expr::ConstStr(a1), Expr::ConstStr(a2)) => {
-                        let l1 = a1.len();
-                        let l2 = a2.len();
-
-                        if l1 <= l2 {
-                            let p: String = a2.chars().take(l1).collect();
-                            Ok(Expr::ConstBool(p == *a1))
-                        } else {
-                            Ok(Expr::ConstBool(false))
-                        }
+                        Ok(Expr::ConstBool(a2.starts_with(a1.as_str())))
                     }
                     _ => Err(format!(
                         "IsPre: invalid argument: arg1 = {:?} arg2 = {:?}",
@@ -290,22 +344,9 @@ impl Eval for Func {
                 let arg2_evaled = arg2.eval(env)?;
 
                 match (&arg1_evaled, &arg2_evaled) {
+                    // (str.suffixof s t) is true iff s is a suffix of t
                     (Expr::ConstStr(a1), Expr::ConstStr(a2)) => {
-                        let l1 = a1.len() as i64;
-                        let l2 = a2.len() as i64;
-
-                        if l1 <= l2 {
-                            let m = l2 - l1 - 1;
-                            let p = a2
-                                .chars()
-                                .skip(m as usize)
-                                .take(l1 as usize)
-                                .collect::<String>();
-
-                            Ok(Expr::ConstBool(p == *a1))
-                        } else {
-                            Ok(Expr::ConstBool(false))
-                        }
+                        Ok(Expr::ConstBool(a2.ends_with(a1.as_str())))
                     }
                     _ => Err(format!(
                         "IsPost: invalid argument: arg1 = {:?} arg2 = {:?}",
@@ -320,7 +361,7 @@ impl Eval for Func {
 
                 match (&arg1_evaled, &arg2_evaled) {
                     (Expr::ConstStr(a1), Expr::ConstStr(a2)) => {
-                        Ok(Expr::ConstBool(a1.contains(a2)))
+                        Ok(Expr::ConstBool(a1.contains(a2.as_str())))
                     }
                     _ => Err(format!(
                         "Contains: invalid argument: arg1 = {:?} arg2 = {:?}",
@@ -336,28 +377,7 @@ impl Eval for Func {
 
                 match (&arg1_evaled, &arg2_evaled, &arg3_evaled) {
                     (Expr::ConstStr(a1), Expr::ConstStr(a2), Expr::ConstInt(a3)) => {
-                        let l1 = a1.len() as i64;
-                        let l2 = a2.len() as i64;
-
-                        if a3 < &l1 {
-                            if l2 == 0 {
-                                Ok(Expr::ConstInt(*a3))
-                            } else {
-                                let b1 = a1
-                                    .chars()
-                                    .skip(*a3 as usize)
-                                    .take((l1 - a3 - 1) as usize)
-                                    .collect::<String>();
-
-                                if let Some(k) = b1.find(a2) {
-                                    Ok(Expr::ConstInt(k as i64))
-                                } else {
-                                    Ok(Expr::ConstInt(-1))
-                                }
-                            }
-                        } else {
-                            Ok(Expr::ConstInt(-1))
-                        }
+                        Ok(Expr::ConstInt(index_of(a1, a2, *a3)))
                     }
                     _ => Err(format!(
                         "Index: invalid argument: arg1 = {:?} arg2 = {:?}, arg3 = {:?}",
@@ -393,13 +413,14 @@ impl Eval for Func {
                 let arg3_evaled = arg3.clone().eval(env)?;
 
                 match (&arg1_evaled, &arg2_evaled, &arg3_evaled) {
+                    // replacing the empty string leaves the input unchanged
+                    (Expr::ConstStr(a1), Expr::ConstStr(a2), Expr::ConstStr(_))
+                        if a2.is_empty() =>
+                    {
+                        Ok(Expr::ConstStr(a1.clone()))
+                    }
                     (Expr::ConstStr(a1), Expr::ConstStr(a2), Expr::ConstStr(a3)) => {
-                        let l1 = a2.len() as i64;
-                        if l1 == 0 {
-                            Func::Append(arg3.clone(), arg1.clone()).eval(env)
-                        } else {
-                            Ok(Expr::ConstStr(a1.replace(a2, a3)))
-                        }
+                        Ok(Expr::ConstStr(a1.replace(a2.as_str(), a3)))
                     }
                     _ => Err(format!(
                         "ReplaceAll: invalid argument: arg1 = {:?} arg2 = {:?}, arg3 = {:?}",
@@ -408,6 +429,116 @@ impl Eval for Func {
                 }
             }
 
+            Self::ReplaceRe(arg1, arg2, arg3) => {
+                let arg1_evaled = arg1.eval(env)?;
+                let arg3_evaled = arg3.eval(env)?;
+
+                match (&arg1_evaled, &arg3_evaled) {
+                    (Expr::ConstStr(a1), Expr::ConstStr(a3)) => {
+                        let re = arg2.translate(env)?;
+                        Ok(Expr::ConstStr(match re.find_shortest(a1, false) {
+                            Some((start, end)) => format!("{}{}{}", &a1[..start], a3, &a1[end..]),
+                            None => a1.clone(),
+                        }))
+                    }
+                    _ => Err(format!(
+                        "ReplaceRe: invalid argument: arg1 = {:?} arg2 = {:?}, arg3 = {:?}",
+                        arg1_evaled, arg2, arg3_evaled
+                    )),
+                }
+            }
+
+            Self::ReplaceReAll(arg1, arg2, arg3) => {
+                let arg1_evaled = arg1.eval(env)?;
+                let arg3_evaled = arg3.eval(env)?;
+
+                match (&arg1_evaled, &arg3_evaled) {
+                    (Expr::ConstStr(a1), Expr::ConstStr(a3)) => {
+                        let re = arg2.translate(env)?;
+                        let mut result = String::new();
+                        let mut rest = a1.as_str();
+                        while let Some((start, end)) = re.find_shortest(rest, true) {
+                            result.push_str(&rest[..start]);
+                            result.push_str(a3);
+                            rest = &rest[end..];
+                        }
+                        result.push_str(rest);
+                        Ok(Expr::ConstStr(result))
+                    }
+                    _ => Err(format!(
+                        "ReplaceReAll: invalid argument: arg1 = {:?} arg2 = {:?}, arg3 = {:?}",
+                        arg1_evaled, arg2, arg3_evaled
+                    )),
+                }
+            }
+
+            Self::InRe(arg1, arg2) => {
+                let arg1_evaled = arg1.eval(env)?;
+
+                match &arg1_evaled {
+                    Expr::ConstStr(a1) => Ok(Expr::ConstBool(arg2.translate(env)?.matches(a1))),
+                    _ => Err(format!(
+                        "InRe: invalid argument: arg1 = {:?} arg2 = {:?}",
+                        arg1_evaled, arg2
+                    )),
+                }
+            }
+
+            Self::IsDigit(arg1) => {
+                let arg1_evaled = arg1.eval(env)?;
+
+                match &arg1_evaled {
+                    Expr::ConstStr(a1) => {
+                        let mut chars = a1.chars();
+                        Ok(Expr::ConstBool(matches!(
+                            (chars.next(), chars.next()),
+                            (Some('0'..='9'), None)
+                        )))
+                    }
+                    _ => Err(format!(
+                        "IsDigit: invalid argument: arg1 = {:?}",
+                        arg1_evaled
+                    )),
+                }
+            }
+
+            Self::ToCode(arg1) => {
+                let arg1_evaled = arg1.eval(env)?;
+
+                match &arg1_evaled {
+                    Expr::ConstStr(a1) => {
+                        let mut chars = a1.chars();
+                        Ok(Expr::ConstInt(match (chars.next(), chars.next()) {
+                            (Some(c), None) => c as i64,
+                            _ => -1,
+                        }))
+                    }
+                    _ => Err(format!(
+                        "ToCode: invalid argument: arg1 = {:?}",
+                        arg1_evaled
+                    )),
+                }
+            }
+
+            Self::FromCode(arg1) => {
+                let arg1_evaled = arg1.eval(env)?;
+
+                match &arg1_evaled {
+                    // code points are restricted to Unicode planes 0-2
+                    Expr::ConstInt(a1) => Ok(Expr::ConstStr(
+                        u32::try_from(*a1)
+                            .ok()
+                            .filter(|n| *n <= 0x2FFFF)
+                            .and_then(char::from_u32)
+                            .map_or(String::new(), String::from),
+                    )),
+                    _ => Err(format!(
+                        "FromCode: invalid argument: arg1 = {:?}",
+                        arg1_evaled
+                    )),
+                }
+            }
+
             Self::LexEq(arg1, arg2) => {
                 let arg1_evaled = arg1.eval(env)?;
                 let arg2_evaled = arg2.eval(env)?;
@@ -421,6 +552,19 @@ impl Eval for Func {
                 }
             }
 
+            Self::LexLt(arg1, arg2) => {
+                let arg1_evaled = arg1.eval(env)?;
+                let arg2_evaled = arg2.eval(env)?;
+
+                match (&arg1_evaled, &arg2_evaled) {
+                    (Expr::ConstStr(a1), Expr::ConstStr(a2)) => Ok(Expr::ConstBool(a1 < a2)),
+                    _ => Err(format!(
+                        "LexLt: invalid argument: arg1 = {:?} arg2 = {:?}",
+                        arg1_evaled, arg2_evaled
+                    )),
+                }
+            }
+
             Self::LexLeq(arg1, arg2) => {
                 let arg1_evaled = arg1.eval(env)?;
                 let arg2_evaled = arg2.eval(env)?;
@@ -450,6 +594,7 @@ impl Eval for Func {
             Self::IntToStr(arg1) => {
                 let arg1 = arg1.eval(env)?;
                 match arg1 {
+                    Expr::ConstInt(n) if n < 0 => Ok(Expr::ConstStr(String::new())),
                     Expr::ConstInt(n) => Ok(Expr::ConstStr(n.to_string())),
                     _ => Err(format!("IntToStr: invalid argument: arg1 = {:?}", arg1)),
                 }
@@ -458,10 +603,13 @@ impl Eval for Func {
             Self::StrToInt(arg1) => {
                 let arg1 = arg1.eval(env)?;
                 match arg1 {
-                    Expr::ConstStr(s) => {
-                        let n = s.parse::<u32>();
-                        Ok(Expr::ConstInt(n.map_or(-1, |n| n.into())))
+                    Expr::ConstStr(s) if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) => {
+                        Ok(Expr::ConstInt(-1))
                     }
+                    Expr::ConstStr(s) => s
+                        .parse::<i64>()
+                        .map(Expr::ConstInt)
+                        .map_err(|_| format!("StrToInt: integer overflow: arg1 = {:?}", s)),
                     _ => Err(format!("StrToInt: invalid argument: arg1 = {:?}", arg1)),
                 }
             }
@@ -469,90 +617,232 @@ impl Eval for Func {
     }
 }
 
+/// The error of an integer operator whose result does not fit in an `i64`
+fn overflow(name: &str, arg1: &Expr, arg2: &Expr) -> String {
+    format!(
+        "{}: integer overflow: arg1 = {:?}, arg2 = {:?}",
+        name, arg1, arg2
+    )
+}
+
+/// `(str.substr s i n)`: the longest substring of `s` of length at most `n`
+/// starting at character `i`, or `""` when `i` is out of range or `n <= 0`.
+fn substr(s: &str, i: i64, n: i64) -> String {
+    if i < 0 || n <= 0 {
+        return String::new();
+    }
+    s.chars().skip(i as usize).take(n as usize).collect()
+}
+
+/// `(str.indexof s t i)`: the character position of the first occurrence of
+/// `t` in `s` at or after `i`, or `-1` if there is none or `i` is out of range.
+fn index_of(s: &str, t: &str, i: i64) -> i64 {
+    if i < 0 || i > s.chars().count() as i64 {
+        return -1;
+    }
+    let start = s.char_indices().nth(i as usize).map_or(s.len(), |(b, _)| b);
+    match s[start..].find(t) {
+        Some(k) => i + s[start..start + k].chars().count() as i64,
+        None => -1,
+    }
+}
+
 impl Translate for RegLang {
-    fn translate(&self) -> Regex {
+    fn translate(&self, env: &HashMap<String, Expr>) -> Result<Re, String> {
         match self {
-            RegLang::Nil => Regex::new(r"[^$]").unwrap(),
-            RegLang::All => Regex::new(r"^[ -~]+$").unwrap(),
-            RegLang::AllChar => unimplemented!(), // Regex::new(r"(?!\A)").unwrap(), // look-around is not supported
-            RegLang::RCall(j) => (*j).clone().translate(),
+            RegLang::Nil => Ok(Re::Empty),
+            RegLang::All => Ok(Re::all()),
+            RegLang::AllChar => Ok(Re::all_char()),
+            RegLang::Var(name) => Err(format!("RegLan variable {} has no value", name)),
+            RegLang::RCall(f) => f.translate(env),
         }
     }
 }
 
 impl Translate for RegFun {
-    fn translate(&self) -> Regex {
+    fn translate(&self, env: &HashMap<String, Expr>) -> Result<Re, String> {
         match self {
             RegFun::Sequence(arg1, arg2) => {
-                let eval1 = arg1.translate();
-                let eval2 = arg2.translate();
-
-                Regex::new(&format!("^ {} {} $", eval1, eval2)).unwrap()
+                Ok(Re::concat(arg1.translate(env)?, arg2.translate(env)?))
             }
+            RegFun::Union(arg1, arg2) => Ok(Re::union(arg1.translate(env)?, arg2.translate(env)?)),
+            RegFun::Inter(arg1, arg2) => Ok(Re::inter(arg1.translate(env)?, arg2.translate(env)?)),
+            RegFun::Star(arg1) => Ok(Re::star(arg1.translate(env)?)),
+            RegFun::One(arg1) => {
+                let eval1 = arg1.translate(env)?;
+                Ok(Re::concat(eval1.clone(), Re::star(eval1)))
+            }
+            RegFun::Opt(arg1) => Ok(Re::union(arg1.translate(env)?, Re::Epsilon)),
+            RegFun::Comp(arg1) => Ok(Re::comp(arg1.translate(env)?)),
+            RegFun::Diff(arg1, arg2) => Ok(Re::inter(
+                arg1.translate(env)?,
+                Re::comp(arg2.translate(env)?),
+            )),
+            RegFun::Power(arg1, n) => Ok(Re::power(arg1.translate(env)?, *n, *n)),
+            RegFun::Loop(arg1, lo, hi) => Ok(Re::power(arg1.translate(env)?, *lo, *hi)),
+            RegFun::FromStr(arg1) => match arg1.clone().eval(env)? {
+                Expr::ConstStr(a) => Ok(Re::literal(&a)),
+                eval1 => Err(format!("str.to_re: invalid argument: arg1 = {:?}", eval1)),
+            },
+            RegFun::Range(arg1, arg2) => {
+                let eval1 = arg1.clone().eval(env)?;
+                let eval2 = arg2.clone().eval(env)?;
 
-            RegFun::Union(arg1, arg2) => {
-                let eval1 = arg1.translate();
-                let eval2 = arg2.translate();
-
-                Regex::new(&format!("^ {}|{} $", eval1, eval2)).unwrap()
+                match (&eval1, &eval2) {
+                    (Expr::ConstStr(a1), Expr::ConstStr(a2)) => {
+                        let mut c1 = a1.chars();
+                        let mut c2 = a2.chars();
+                        match (c1.next(), c1.next(), c2.next(), c2.next()) {
+                            // only singleton bounds denote a non-empty range
+                            (Some(lo), None, Some(hi), None) => Ok(Re::range(lo, hi)),
+                            _ => Ok(Re::Empty),
+                        }
+                    }
+                    _ => Err(format!(
+                        "re.range: invalid argument: arg1 = {:?}, arg2 = {:?}",
+                        eval1, eval2
+                    )),
+                }
             }
+        }
+    }
+}
 
-            RegFun::Inter(arg1, arg2) => {
-                let eval1 = arg1.translate();
-                let eval2 = arg2.translate();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                Regex::new(&format!("^ (?= {})(?= {}) $", eval1, eval2)).unwrap()
-            }
+    fn str(s: &str) -> Expr {
+        Expr::ConstStr(s.into())
+    }
 
-            RegFun::Star(arg1) => {
-                let eval1 = arg1.translate();
+    fn int(n: i64) -> Expr {
+        Expr::ConstInt(n)
+    }
 
-                Regex::new(&format!("({})*", eval1)).unwrap()
-            }
+    fn eval(f: Func) -> Term {
+        f.eval(&HashMap::new())
+    }
 
-            RegFun::One(arg1) => {
-                let eval1 = arg1.translate();
+    // Cases follow the definitions in assets/theories/UnicodeStrings.smt2
+
+    #[test]
+    fn substr_takes_a_length() {
+        assert_eq!(
+            eval(Func::SubStr(str("abcdef"), int(1), int(3))),
+            Ok(str("bcd"))
+        );
+        assert_eq!(
+            eval(Func::SubStr(str("abcdef"), int(4), int(10))),
+            Ok(str("ef"))
+        );
+        assert_eq!(eval(Func::SubStr(str("abc"), int(3), int(1))), Ok(str("")));
+        assert_eq!(eval(Func::SubStr(str("abc"), int(-1), int(2))), Ok(str("")));
+        assert_eq!(eval(Func::SubStr(str("abc"), int(0), int(0))), Ok(str("")));
+        assert_eq!(eval(Func::SubStr(str("äöü"), int(1), int(1))), Ok(str("ö")));
+    }
 
-                Regex::new(&format!("({})+", eval1)).unwrap()
-            }
+    #[test]
+    fn at_and_len_count_characters() {
+        assert_eq!(eval(Func::StrAt(str("αβγ"), int(2))), Ok(str("γ")));
+        assert_eq!(eval(Func::StrAt(str("abc"), int(3))), Ok(str("")));
+        assert_eq!(eval(Func::StrAt(str("abc"), int(-1))), Ok(str("")));
+        assert_eq!(eval(Func::StrLen(str("αβγ"))), Ok(int(3)));
+    }
 
-            RegFun::Opt(arg1) => {
-                let eval1 = arg1.translate();
+    #[test]
+    fn prefix_and_suffix() {
+        let t = Expr::ConstBool(true);
+        let f = Expr::ConstBool(false);
+        assert_eq!(eval(Func::IsPre(str("ab"), str("abc"))), Ok(t.clone()));
+        assert_eq!(eval(Func::IsPre(str("abc"), str("ab"))), Ok(f.clone()));
+        assert_eq!(eval(Func::IsPost(str("bc"), str("abc"))), Ok(t.clone()));
+        assert_eq!(eval(Func::IsPost(str("abc"), str("abc"))), Ok(t.clone()));
+        assert_eq!(eval(Func::IsPost(str(""), str("abc"))), Ok(t));
+        assert_eq!(eval(Func::IsPost(str("ab"), str("abc"))), Ok(f));
+    }
 
-                Regex::new(&format!("{} | \"\"", eval1)).unwrap()
-            }
+    #[test]
+    fn indexof_is_absolute() {
+        assert_eq!(
+            eval(Func::Index(str("abcabc"), str("c"), int(0))),
+            Ok(int(2))
+        );
+        assert_eq!(
+            eval(Func::Index(str("abcabc"), str("c"), int(3))),
+            Ok(int(5))
+        );
+        assert_eq!(
+            eval(Func::Index(str("abcabc"), str("d"), int(0))),
+            Ok(int(-1))
+        );
+        assert_eq!(eval(Func::Index(str("abc"), str(""), int(3))), Ok(int(3)));
+        assert_eq!(eval(Func::Index(str("abc"), str(""), int(4))), Ok(int(-1)));
+        assert_eq!(
+            eval(Func::Index(str("abc"), str("a"), int(-1))),
+            Ok(int(-1))
+        );
+        assert_eq!(eval(Func::Index(str("αβγ"), str("γ"), int(1))), Ok(int(2)));
+    }
 
-            RegFun::FromStr(arg1) => {
-                let j = HashMap::new();
-                let eval1 = arg1.clone().eval(&j).clone();
+    #[test]
+    fn replace_and_replace_all() {
+        assert_eq!(
+            eval(Func::Replace(str("abab"), str("b"), str("x"))),
+            Ok(str("axab"))
+        );
+        assert_eq!(
+            eval(Func::Replace(str("abc"), str(""), str("x"))),
+            Ok(str("xabc"))
+        );
+        assert_eq!(
+            eval(Func::ReplaceAll(str("abab"), str("b"), str("x"))),
+            Ok(str("axax"))
+        );
+        assert_eq!(
+            eval(Func::ReplaceAll(str("abc"), str(""), str("x"))),
+            Ok(str("abc"))
+        );
+    }
 
-                match eval1 {
-                    Ok(Expr::ConstStr(a)) => Regex::new(&a.to_string()).unwrap(),
-                    _ => RegLang::Nil.translate(),
-                }
-            }
+    #[test]
+    fn int_conversions() {
+        assert_eq!(eval(Func::StrToInt(str("00123"))), Ok(int(123)));
+        assert_eq!(eval(Func::StrToInt(str("-123"))), Ok(int(-1)));
+        assert_eq!(eval(Func::StrToInt(str(""))), Ok(int(-1)));
+        // integers overflow into an error, as in StrToInt, rather than a panic
+        assert!(eval(Func::Add(int(i64::MAX), int(1))).is_err());
+        assert!(eval(Func::Min(int(i64::MIN), int(1))).is_err());
+        assert!(eval(Func::Mult(int(i64::MAX), int(2))).is_err());
+        assert!(eval(Func::Div(int(i64::MIN), int(-1))).is_err());
+        assert!(eval(Func::Mod(int(i64::MIN), int(-1))).is_err());
+        assert!(eval(Func::Abs(int(i64::MIN))).is_err());
+        assert!(eval(Func::NegI(int(i64::MIN))).is_err());
+        assert_eq!(eval(Func::StrToInt(str("9876543210"))), Ok(int(9876543210)));
+        assert_eq!(eval(Func::IntToStr(int(123))), Ok(str("123")));
+        assert_eq!(eval(Func::IntToStr(int(-123))), Ok(str("")));
+    }
 
-            RegFun::Range(arg1, arg2) => {
-                let j = HashMap::new();
-                let eval1 = arg1.clone().eval(&j);
-                let eval2 = arg2.clone().eval(&j);
-
-                match (eval1, eval2) {
-                    (Ok(Expr::ConstStr(a)), Ok(Expr::ConstStr(_))) => {
-                        let l1 = a.len();
-                        let l2 = a.len();
-
-                        if l1 == l2 && l1 == 1 {
-                            // I know this was supposed to take a range between
-                            // any two same size strings but I couldn't figure that out just yet
-                            Regex::new(&format!("[{}-{}]", l1, l2)).unwrap()
-                        } else {
-                            RegLang::Nil.translate()
-                        }
-                    }
-                    _ => RegLang::Nil.translate(),
-                }
-            }
-        }
+    #[test]
+    fn code_points_and_digits() {
+        let t = Expr::ConstBool(true);
+        let f = Expr::ConstBool(false);
+        assert_eq!(eval(Func::IsDigit(str("7"))), Ok(t));
+        assert_eq!(eval(Func::IsDigit(str("77"))), Ok(f.clone()));
+        assert_eq!(eval(Func::IsDigit(str("x"))), Ok(f));
+        assert_eq!(eval(Func::ToCode(str("A"))), Ok(int(65)));
+        assert_eq!(eval(Func::ToCode(str("AB"))), Ok(int(-1)));
+        assert_eq!(eval(Func::FromCode(int(0x3B1))), Ok(str("α")));
+        assert_eq!(eval(Func::FromCode(int(0x30000))), Ok(str("")));
+        assert_eq!(eval(Func::FromCode(int(-1))), Ok(str("")));
+    }
+
+    #[test]
+    fn euclidean_division() {
+        assert_eq!(eval(Func::Div(int(-7), int(2))), Ok(int(-4)));
+        assert_eq!(eval(Func::Mod(int(-7), int(2))), Ok(int(1)));
+        assert_eq!(eval(Func::Div(int(7), int(-2))), Ok(int(-3)));
+        assert_eq!(eval(Func::Mod(int(7), int(-2))), Ok(int(1)));
+        assert!(eval(Func::Div(int(1), int(0))).is_err());
     }
 }
@@ -1,4 +1,5 @@
 use crate::language::Expr;
+use crate::slia::{Op, Tag};
 use crate::Spec;
 use std::cmp;
 
@@ -18,11 +19,11 @@ fn unwrap_vec(vecs: Vec<Option<(Expr, Expr)>>) -> Spec {
     }
 }
 
-pub fn invert(spec: &Spec, symbol: &str) -> Spec {
+pub fn invert(spec: &Spec, tag: Tag) -> Spec {
     match spec {
         Spec::Examples(a) => {
-            match symbol {
-                "substring0" => {
+            match (tag.op, tag.arg) {
+                (Op::SubStr, 0) => {
                     let temp: Vec<Option<(Expr, Expr)>> = a
                         .iter()
                         .map(|(i, o)| match (i, o) {
@@ -40,12 +41,14 @@ pub fn invert(spec: &Spec, symbol: &str) -> Spec {
                     unwrap_vec(temp)
                 }
 
-                "substring1" => {
+                (Op::SubStr, 1) => {
                     let temp: Vec<Option<(Expr, Expr)>> = a
                         .iter()
                         .map(|(i, o)| match (i, o) {
                             (Expr::ConstStr(inn), Expr::ConstStr(out)) => {
-                                inn.find(out).map(|k| (i.clone(), Expr::ConstInt(k as i64)))
+                                inn.find(out.as_str()).map(|k| {
+                                    (i.clone(), Expr::ConstInt(inn[..k].chars().count() as i64))
+                                })
                             }
                             _ => None,
                         })
@@ -54,13 +57,13 @@ pub fn invert(spec: &Spec, symbol: &str) -> Spec {
                     unwrap_vec(temp)
                 }
 
-                "substring2" => {
+                (Op::SubStr, 2) => {
                     let temp: Vec<Option<(Expr, Expr)>> = a
                         .iter()
                         .map(|(i, o)| match (i, o) {
                             (Expr::ConstStr(inn), Expr::ConstStr(out)) => inn
-                                .find(out)
-                                .map(|k| (i.clone(), Expr::ConstInt((k + out.len()) as i64))),
+                                .find(out.as_str())
+                                .map(|_| (i.clone(), Expr::ConstInt(out.chars().count() as i64))),
                             _ => None,
                         })
                         .collect();
@@ -68,7 +71,7 @@ pub fn invert(spec: &Spec, symbol: &str) -> Spec {
                     unwrap_vec(temp)
                 }
 
-                "append0" => {
+                (Op::Append, 0) => {
                     let mut j = 0; // this is so that we can track if the append is being used at the beginning or end
 
                     let temp: Vec<Option<(Expr, Expr)>> = a
@@ -115,7 +118,7 @@ pub fn invert(spec: &Spec, symbol: &str) -> Spec {
                     unwrap_vec(temp)
                 }
 
-                "append1" => {
+                (Op::Append, 1) => {
                     let mut j = 0; // this is so that we can track if the append is being used at the beginning or end
 
                     let temp: Vec<Option<(Expr, Expr)>> = a
@@ -160,7 +163,7 @@ pub fn invert(spec: &Spec, symbol: &str) -> Spec {
                     unwrap_vec(temp)
                 }
 
-                "strlen0" => {
+                (Op::StrLen, 0) => {
                     let temp: Vec<Option<(Expr, Expr)>> = a
                         .iter()
                         .map(|(i, o)| {
@@ -184,7 +187,7 @@ pub fn invert(spec: &Spec, symbol: &str) -> Spec {
                     unwrap_vec(temp)
                 }
 
-                "replace0" => {
+                (Op::Replace, 0) => {
                     // wait ok I'm gonna impose the rediculous idea that if the code could have been created by append in either direction,
                     // we will assume the incorrect function has been chosen.
                     // Also I would recommend we just state that if the previous string is a member of the new string it is also incorrect.
@@ -206,7 +209,7 @@ pub fn invert(spec: &Spec, symbol: &str) -> Spec {
                     unwrap_vec(temp)
                 }
 
-                "replace1" => {
+                (Op::Replace, 1) => {
                     let temp: Vec<Option<(Expr, Expr)>> = a
                         .iter()
                         .map(|(i, o)| {
@@ -268,7 +271,7 @@ pub fn invert(spec: &Spec, symbol: &str) -> Spec {
                     unwrap_vec(temp)
                 }
 
-                "replace2" => {
+                (Op::Replace, 2) => {
                     let temp: Vec<Option<(Expr, Expr)>> = a
                         .iter()
                         .map(|(i, o)| {
@@ -330,7 +333,7 @@ pub fn invert(spec: &Spec, symbol: &str) -> Spec {
                     unwrap_vec(temp)
                 }
 
-                "strat0" => {
+                (Op::StrAt, 0) => {
                     let temp: Vec<Option<(Expr, Expr)>> = a
                         .iter()
                         .map(|(i, o)| {
@@ -354,7 +357,7 @@ pub fn invert(spec: &Spec, symbol: &str) -> Spec {
                     unwrap_vec(temp)
                 }
 
-                "strat1" => {
+                (Op::StrAt, 1) => {
                     let temp: Vec<Option<(Expr, Expr)>> = a
                         .iter()
                         .map(|(i, o)| {
@@ -383,7 +386,7 @@ pub fn invert(spec: &Spec, symbol: &str) -> Spec {
                     }
                 }
 
-                "index0" => {
+                (Op::Index, 0) => {
                     let temp: Vec<Option<(Expr, Expr)>> = a
                         .iter()
                         .map(|(i, o)| {
@@ -407,7 +410,7 @@ pub fn invert(spec: &Spec, symbol: &str) -> Spec {
                     unwrap_vec(temp)
                 }
 
-                "index1" => {
+                (Op::Index, 1) => {
                     let temp: Vec<Option<(Expr, Expr)>> = a
                         .iter()
                         .map(|(i, o)| {
@@ -436,7 +439,7 @@ pub fn invert(spec: &Spec, symbol: &str) -> Spec {
                     }
                 }
 
-                "index2" => {
+                (Op::Index, 2) => {
                     let temp: Vec<Option<(Expr, Expr)>> = a
                         .iter()
                         .map(|(i, o)| {
@@ -1,9 +1,9 @@
-use regex::Regex;
+use crate::automaton::Re;
 use std::collections::HashMap;
 
 pub type Term = Result<Expr, String>;
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum Func {
     Append(Expr, Expr),
     StrLen(Expr),
@@ -15,9 +15,17 @@ pub enum Func {
     Index(Expr, Expr, Expr),
     Replace(Expr, Expr, Expr),
     ReplaceAll(Expr, Expr, Expr),
+    ReplaceRe(Expr, RegLang, Expr),
+    ReplaceReAll(Expr, RegLang, Expr),
+    InRe(Expr, RegLang),
+    IsDigit(Expr),
+    ToCode(Expr),
+    FromCode(Expr),
 
     Leq(Expr, Expr),
     Geq(Expr, Expr),
+    Lt(Expr, Expr),
+    Gt(Expr, Expr),
     Eql(Expr, Expr),
     Add(Expr, Expr),
     Min(Expr, Expr),
@@ -30,7 +38,10 @@ pub enum Func {
     NegB(Expr),
     And(Expr, Expr),
     Or(Expr, Expr),
+    Xor(Expr, Expr),
+    Implies(Expr, Expr),
     LexEq(Expr, Expr),
+    LexLt(Expr, Expr),
     LexLeq(Expr, Expr),
     LexGeq(Expr, Expr),
 
@@ -38,15 +49,17 @@ pub enum Func {
     IntToStr(Expr),
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum RegLang {
     Nil,
     All,
     AllChar,
+    /// A `RegLan` parameter, which has no value in an `Expr` environment
+    Var(String),
     RCall(Box<RegFun>),
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum RegFun {
     Sequence(RegLang, RegLang),
     Union(RegLang, RegLang),
@@ -56,13 +69,17 @@ pub enum RegFun {
     Opt(RegLang),
     Range(Expr, Expr),
     FromStr(Expr),
+    Comp(RegLang),
+    Diff(RegLang, RegLang),
+    Power(RegLang, u32),
+    Loop(RegLang, u32, u32),
 }
 
 pub trait Translate {
-    fn translate(&self) -> Regex;
+    fn translate(&self, env: &HashMap<String, Expr>) -> Result<Re, String>;
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum Expr {
     ConstStr(String),
     ConstInt(i64),
@@ -80,4 +97,60 @@ impl Expr {
     pub fn call(arg: Func) -> Expr {
         Expr::Call(Box::new(arg))
     }
+
+    /// The number of nodes, not counting regular expression arguments
+    pub fn size(&self) -> usize {
+        match self {
+            Expr::Call(f) => 1 + f.args().into_iter().map(Expr::size).sum::<usize>(),
+            Expr::If(c, t, e) => 1 + c.size() + t.size() + e.size(),
+            _ => 1,
+        }
+    }
+}
+
+impl Func {
+    /// The `Expr` arguments, in order
+    pub fn args(&self) -> Vec<&Expr> {
+        use Func::*;
+        match self {
+            StrLen(a)
+            | IsDigit(a)
+            | ToCode(a)
+            | FromCode(a)
+            | Abs(a)
+            | NegI(a)
+            | NegB(a)
+            | StrToInt(a)
+            | IntToStr(a)
+            | InRe(a, _) => vec![a],
+            Append(a, b)
+            | StrAt(a, b)
+            | IsPre(a, b)
+            | IsPost(a, b)
+            | Contains(a, b)
+            | Leq(a, b)
+            | Geq(a, b)
+            | Lt(a, b)
+            | Gt(a, b)
+            | Eql(a, b)
+            | Add(a, b)
+            | Min(a, b)
+            | Mult(a, b)
+            | Div(a, b)
+            | Mod(a, b)
+            | And(a, b)
+            | Or(a, b)
+            | Xor(a, b)
+            | Implies(a, b)
+            | LexEq(a, b)
+            | LexLt(a, b)
+            | LexLeq(a, b)
+            | LexGeq(a, b)
+            | ReplaceRe(a, _, b)
+            | ReplaceReAll(a, _, b) => vec![a, b],
+            SubStr(a, b, c) | Index(a, b, c) | Replace(a, b, c) | ReplaceAll(a, b, c) => {
+                vec![a, b, c]
+            }
+        }
+    }
 }
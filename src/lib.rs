@@ -1,18 +1,34 @@
+pub mod apply;
+pub mod arena;
+pub mod automaton;
+pub mod bank;
+pub mod codegen;
+pub mod disambiguate;
 pub mod enumerate;
 pub mod interpreter;
 pub mod inverse;
 pub mod language;
+pub mod regex;
+pub mod search;
+pub mod session;
+pub mod simplify;
+pub mod slia;
 pub mod sygus;
+pub mod table;
 
-use egg::{self, CostFunction, EGraph, Id, Language, RecExpr, Rewrite, Runner, SymbolLang};
+use bank::Bank;
+use egg::{self, CostFunction, EGraph, Id, Language, RecExpr, Rewrite, Runner};
 use egg::{rewrite as rw, Analysis, DidMerge};
-use language::{Eval, Expr, Func::*, Term};
+use language::{Expr, Func::*, Term};
 use std::collections::HashMap;
 
-pub type SLIALang = SymbolLang;
+pub use slia::SLIALang;
+use slia::Weights;
 #[derive(Clone, Debug, Default, PartialEq)]
 pub enum Spec {
     Examples(Vec<(Expr, Expr)>),
+    // a program class built from literals alone, folded to its value
+    Constant(Expr),
     Impossible,
     #[default]
     Indeterminate,
@@ -24,88 +40,220 @@ impl Analysis<SLIALang> for Spec {
 
     fn merge(&mut self, to: &mut Self::Data, from: Self::Data) -> DidMerge {
         match (to, from) {
-            (Impossible, Impossible) => DidMerge(false, false),
-            (_, _) => DidMerge(true, true), // fixme
+            // unchanged data must not be reported as changed, or merges
+            // would propagate around cycles forever
+            (to, from) if *to == from => DidMerge(false, false),
+            // alternatives of a hole may fold to distinct constants, and
+            // only one of them has to meet its examples: EvalCostFn rules
+            // out the others, one alternative at a time
+            (to @ Constant(_), Constant(_)) => {
+                *to = Indeterminate;
+                DidMerge(true, true)
+            }
+            (to @ Indeterminate, from @ Constant(_)) => {
+                *to = from;
+                DidMerge(true, false)
+            }
+            (Constant(_), Indeterminate) => DidMerge(false, true),
+            (to @ Constant(_), Impossible) => {
+                *to = Impossible;
+                DidMerge(true, false)
+            }
+            // any other data is kept as it is
+            (_, _) => DidMerge(false, true),
         }
     }
 
     fn make(egraph: &EGraph<SLIALang, Self>, enode: &SLIALang) -> Self::Data {
-        match enode.op.as_str() {
+        match enode {
             // inverse semantics here
-            "inv" => {
-                inverse::invert(
-                    /*spec*/ &egraph[enode.children[1]].data,
-                    egraph[enode.children[0]].nodes[0].op.as_str(), /*tag */
-                )
+            SLIALang::Inv(tag, spec) => inverse::invert(&egraph[*spec].data, *tag),
+            _ => fold_node(egraph, enode).map_or(Indeterminate, Constant),
+        }
+    }
+
+    /// Adds the literal of a constant class. If a hole in the class has
+    /// examples the constant does not satisfy, only the alternatives folded
+    /// to it are ruled out, by [`EvalCostFn`], and the hole stays open.
+    fn modify(egraph: &mut EGraph<SLIALang, Self>, id: Id) {
+        let Constant(value) = &egraph[id].data else {
+            return;
+        };
+        if contradicts(egraph, id, value) {
+            egraph[id].data = Indeterminate;
+        } else if let Some(literal) = simplify::literal(value) {
+            let added = egraph.add(literal);
+            egraph.union(id, added);
+        }
+    }
+}
+
+/// Whether a hole in class `id` has examples that `value` does not satisfy
+fn contradicts(egraph: &EGraph<SLIALang, Spec>, id: Id, value: &Expr) -> bool {
+    egraph[id].nodes.iter().any(|node| match node {
+        SLIALang::Hole(_, spec) => match &egraph[*spec].data {
+            Examples(ios) => ios.iter().any(|(_, o)| o != value),
+            _ => false,
+        },
+        _ => false,
+    })
+}
+
+/// The value of `enode` folded from the constant classes it applies to
+fn fold_node(egraph: &EGraph<SLIALang, Spec>, enode: &SLIALang) -> Option<Expr> {
+    simplify::fold(enode, |id| match &egraph[id].data {
+        Constant(e) => Some(e.clone()),
+        _ => None,
+    })
+}
+
+/// Fills the hole e-classes of a saturated e-graph from `bank`, keyed by
+/// canonical id. A class holding several holes takes the smallest fill.
+pub fn fill_holes(egraph: &EGraph<SLIALang, Spec>, bank: &Bank) -> HashMap<Id, Expr> {
+    let mut fills = HashMap::new();
+    for class in egraph.classes() {
+        let fill = class
+            .nodes
+            .iter()
+            .filter_map(|node| match node {
+                SLIALang::Hole(sort, spec) => match &egraph[*spec].data {
+                    Examples(ios) => bank.fill(&sort.to_string(), ios),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .min_by_key(|e| e.size());
+        if let Some(e) = fill {
+            fills.insert(class.id, e.clone());
+        }
+    }
+    fills
+}
+
+/// Unions each class of `egraph` whose fill is a constant with the literal
+/// of that constant, and returns `fills` keyed by the new canonical ids.
+///
+/// The rules only build operators over holes, so this is what lets constant
+/// folding reach the operators above filled holes: those that fold to a value
+/// their examples rule out are then left out of extraction.
+pub fn fold_fills(
+    egraph: &mut EGraph<SLIALang, Spec>,
+    fills: &HashMap<Id, Expr>,
+) -> HashMap<Id, Expr> {
+    let mut ids: Vec<&Id> = fills.keys().collect();
+    ids.sort();
+    for id in ids {
+        if let Some(literal) = simplify::literal(&fills[id]) {
+            let added = egraph.add(literal);
+            egraph.union(*id, added);
+        }
+    }
+    egraph.rebuild();
+    fills
+        .iter()
+        .map(|(id, fill)| (egraph.find(*id), fill.clone()))
+        .collect()
+}
+
+/// Unions the classes of `egraph` that compute the same outputs on the
+/// inputs of `bank`, known from their fill or their constant, and returns
+/// `fills` keyed by the new canonical ids. Like observational equivalence in
+/// bottom-up search, this keeps one class per behaviour on the examples.
+pub fn merge_equivalent(
+    egraph: &mut EGraph<SLIALang, Spec>,
+    fills: &HashMap<Id, Expr>,
+    bank: &Bank,
+) -> HashMap<Id, Expr> {
+    let mut classes: HashMap<Vec<Expr>, Id> = HashMap::new();
+    let mut ids: Vec<Id> = egraph.classes().map(|class| class.id).collect();
+    // the same classes merge the same way from run to run
+    ids.sort();
+    for id in ids {
+        let outputs = match (fills.get(&id), &egraph[id].data) {
+            (Some(fill), _) => bank.outputs(fill),
+            (None, Constant(value)) => Some(vec![value.clone(); bank.inputs().len()]),
+            _ => None,
+        };
+        if let Some(outputs) = outputs {
+            match classes.get(&outputs) {
+                Some(other) => {
+                    egraph.union(*other, id);
+                }
+                None => {
+                    classes.insert(outputs, id);
+                }
+            }
+        }
+    }
+    egraph.rebuild();
+
+    let mut merged: HashMap<Id, Expr> = HashMap::new();
+    for (id, fill) in fills {
+        let id = egraph.find(*id);
+        match merged.get(&id) {
+            Some(best) if best.size() <= fill.size() => {}
+            _ => {
+                merged.insert(id, fill.clone());
             }
-            _ => Indeterminate,
         }
     }
+    merged
 }
 
 pub struct EvalCostFn<'a> {
     egraph: &'a EGraph<SLIALang, Spec>,
-    components: &'a HashMap<String, Vec<Expr>>,
-    component_fills: &'a mut HashMap<Id, Expr>,
+    fills: &'a HashMap<Id, Expr>,
+    weights: &'a Weights,
 }
 
 impl<'a> EvalCostFn<'a> {
     pub fn new(
         egraph: &'a EGraph<SLIALang, Spec>,
-        components: &'a HashMap<String, Vec<Expr>>,
-        component_fills: &'a mut HashMap<Id, Expr>,
+        fills: &'a HashMap<Id, Expr>,
+        weights: &'a Weights,
     ) -> Self {
         Self {
             egraph,
-            components,
-            component_fills,
+            fills,
+            weights,
         }
     }
 }
 
 impl<'a> CostFunction<SLIALang> for EvalCostFn<'a> {
-    // (unfillable_holes, num_holes, size)
+    // (unfillable_holes, num_holes, weighted size)
     type Cost = (usize, usize, usize);
     fn cost<C>(&mut self, enode: &SLIALang, mut costs: C) -> Self::Cost
     where
         C: FnMut(Id) -> Self::Cost,
     {
-        let (mut unfillable, mut holes, size) = (0, 0, 1);
+        let (mut unfillable, mut holes, size) = (0, 0, self.weights.node(enode));
 
         let class = self.egraph.lookup(enode.clone()).unwrap();
+        // nothing in a class ruled out by constant folding can be used
+        if self.egraph[class].data == Impossible {
+            unfillable += 1;
+        }
+
+        // an alternative that folds to a value the examples rule out
+        if !matches!(enode, SLIALang::Hole(..)) {
+            let value = fold_node(self.egraph, enode);
+            if value.is_some_and(|value| contradicts(self.egraph, class, &value)) {
+                unfillable += 1;
+            }
+        }
+
         //check if enode *is* a hole
-        let symbol = enode.op.as_str();
-        match symbol {
-            "Int" | "Bool" | "String" => {
-                // try to fill hole - if failure increment hole counter
-
-                let spec = &self.egraph[enode.children[0]].data;
-                match spec {
-                    Impossible => unfillable += 1,
-                    Indeterminate => holes += 1,
-                    Examples(ios) => {
-                        // try to fill
-                        let mut found_fill = false;
-                        for e in self.components[symbol].iter() {
-                            if ios.iter().all(|(i, o)| {
-                                let mut env: HashMap<String, Expr> = HashMap::new();
-                                env.insert(String::from("name"), i.clone());
-                                e.clone().eval(&env) == Ok(o.clone())
-                            }) {
-                                found_fill = true;
-                                self.component_fills.insert(class, e.clone());
-                                break;
-                            }
-                        }
-                        /* on failure */
-                        if !found_fill {
-                            unfillable += 1
-                        };
-                    }
-                };
+        if let SLIALang::Hole(_, spec) = enode {
+            if let Some(fill) = self.fills.get(&class) {
+                // a filled hole costs as much as the component filling it
+                return (0, 0, self.weights.expr(fill));
             }
-            _ => (),
-        };
+            match &self.egraph[*spec].data {
+                Impossible | Examples(_) => unfillable += 1,
+                Constant(_) | Indeterminate => holes += 1,
+            };
+        }
         enode.fold((unfillable, holes, size), |(a, b, c), id| {
             let (a1, b1, c1) = costs(id);
             (a + a1, b + b1, c + c1)
@@ -115,34 +263,40 @@ impl<'a> CostFunction<SLIALang> for EvalCostFn<'a> {
 
 pub fn grammar_rules() -> Vec<Rewrite<SLIALang, Spec>> {
     vec![
-        rw!("eq"; "(Bool ?s)" => "(Eql (Int (inv eq0 ?s)) (Int (inv eq1 ?s)))"),
-        //rw!("gt"; "(Bool ?s)" => "(> (Int (inv gt0 ?s)) (Int (inv gt1 ?s)))"),
-        rw!("ge"; "(Bool ?s)" => "(Geq (Int (inv ge0 ?s)) (Int (inv ge1 ?s)))"),
-        //rw!("lt"; "(Bool ?s)" => "(< (Int (inv lt0 ?s)) (Int (inv lt1 ?s)))"),
-        rw!("le"; "(Bool ?s)" => "(Leq (Int (inv le0 ?s)) (Int (inv le1 ?s)))"),
-        rw!("substr"; "(String ?s)" => "(SubStr (String (inv substring0 ?s)) (Int (inv substring1 ?s)) (Int(inv substring2 ?s)))"),
-        rw!("app"; "(String ?s)" => "(Append (String (inv append0  ?s)) (String (inv append1 ?s)))"),
-        rw!("replace"; "(String ?s)" => "(Replace (String (inv replace0 ?s)) (String (inv replace1 ?s)) (String (inv replace2 ?s)))"),
-        rw!("strlen"; "(Int ?s)" => "(StrLen (String (inv strlen0 ?s)))"),
-        rw!("StrAt"; "(String ?s)" => "(StrAt (String (inv strat0 ?s)) (Int (inv strat0 ?s)) )"),
-        rw!("IsPre"; "(Bool ?s)" => "(IsPre (String (inv na ?s)) (String (inv na ?s)) )"),
-        rw!("IsPost"; "(Bool ?s)" => "(IsPost (String (inv na ?s)) (String (inv na ?s)) )"),
-        rw!("Contains"; "(Bool ?s)" => "(Contains (String (inv na ?s)) (String (inv na ?s)) )"),
-        rw!("Index"; "(Int ?s)" => "(Index (String (inv index0 ?s)) (String (inv index1 ?s)) (Int (inv index2 ?s)))"),
-        rw!("replaceall"; "(String ?s)" => "(ReplaceAll (String (inv repall0 ?s)) (String (inv repall1 ?s)) (String (inv repall2 ?s)))"),
-        rw!("Add"; "(Int ?s)" => "(Add (Int (inv add0 ?s)) (Int (inv add1 ?s)) )"),
-        rw!("Min"; "(Int ?s)" => "(Min (Int (inv min0 ?s)) (Int (inv min1 ?s)) )"),
-        rw!("Mult"; "(Int ?s)" => "(Mult (Int (inv mult0 ?s)) (Int (inv mult1 ?s)) )"),
-        rw!("Div"; "(Int ?s)" => "(Div (Int (inv div0 ?s)) (Int (inv div1 ?s)) )"),
-        rw!("Abs"; "(Int ?s)" => "(Abs (Int (inv abs0 ?s)) (Int (inv abs1 ?s)) )"),
-        rw!("Mod"; "(Int ?s)" => "(Mod (Int (inv mod0 ?s)) (Int (inv mod1 ?s)) )"),
-        rw!("NegI"; "(Int ?s)" => "(NegI (Int (inv negi0 ?s)) )"),
-        rw!("NegB"; "(Bool ?s)" => "(NegB (Bool (inv negb0 ?s)) )"),
-        rw!("And"; "(Bool ?s)" => "(And (Bool (inv and0 ?s)) (Bool (inv and1 ?s)) )"),
-        rw!("Or"; "(Bool ?s)" => "(Or (Bool (inv or0 ?s)) (Bool (inv or1 ?s)) )"),
-        rw!("LexEq"; "(Bool ?s)" => "(LexEq (String (inv lexeq0 ?s)) (String (inv lexeq1 ?s)) )"),
-        rw!("LexLeq"; "(Bool ?s)" => "(LexLeq (String (inv lexleq0 ?s)) (String (inv lexleq1 ?s)) )"),
-        rw!("LexGeq"; "(Bool ?s)" => "(LexGeq (String (inv lexgeq0 ?s)) (String (inv lexgeq1 ?s)) )"),
+        rw!("eq"; "(Bool ?s)" => "(Eql (Int (inv.Eql.0 ?s)) (Int (inv.Eql.1 ?s)))"),
+        rw!("gt"; "(Bool ?s)" => "(Gt (Int (inv.Gt.0 ?s)) (Int (inv.Gt.1 ?s)))"),
+        rw!("ge"; "(Bool ?s)" => "(Geq (Int (inv.Geq.0 ?s)) (Int (inv.Geq.1 ?s)))"),
+        rw!("lt"; "(Bool ?s)" => "(Lt (Int (inv.Lt.0 ?s)) (Int (inv.Lt.1 ?s)))"),
+        rw!("le"; "(Bool ?s)" => "(Leq (Int (inv.Leq.0 ?s)) (Int (inv.Leq.1 ?s)))"),
+        rw!("substr"; "(String ?s)" => "(SubStr (String (inv.SubStr.0 ?s)) (Int (inv.SubStr.1 ?s)) (Int (inv.SubStr.2 ?s)))"),
+        rw!("app"; "(String ?s)" => "(Append (String (inv.Append.0 ?s)) (String (inv.Append.1 ?s)))"),
+        rw!("replace"; "(String ?s)" => "(Replace (String (inv.Replace.0 ?s)) (String (inv.Replace.1 ?s)) (String (inv.Replace.2 ?s)))"),
+        rw!("strlen"; "(Int ?s)" => "(StrLen (String (inv.StrLen.0 ?s)))"),
+        rw!("StrAt"; "(String ?s)" => "(StrAt (String (inv.StrAt.0 ?s)) (Int (inv.StrAt.1 ?s)))"),
+        rw!("IsPre"; "(Bool ?s)" => "(IsPre (String (inv.IsPre.0 ?s)) (String (inv.IsPre.1 ?s)))"),
+        rw!("IsPost"; "(Bool ?s)" => "(IsPost (String (inv.IsPost.0 ?s)) (String (inv.IsPost.1 ?s)))"),
+        rw!("Contains"; "(Bool ?s)" => "(Contains (String (inv.Contains.0 ?s)) (String (inv.Contains.1 ?s)))"),
+        rw!("Index"; "(Int ?s)" => "(Index (String (inv.Index.0 ?s)) (String (inv.Index.1 ?s)) (Int (inv.Index.2 ?s)))"),
+        rw!("replaceall"; "(String ?s)" => "(ReplaceAll (String (inv.ReplaceAll.0 ?s)) (String (inv.ReplaceAll.1 ?s)) (String (inv.ReplaceAll.2 ?s)))"),
+        rw!("Add"; "(Int ?s)" => "(Add (Int (inv.Add.0 ?s)) (Int (inv.Add.1 ?s)))"),
+        rw!("Min"; "(Int ?s)" => "(Min (Int (inv.Min.0 ?s)) (Int (inv.Min.1 ?s)))"),
+        rw!("Mult"; "(Int ?s)" => "(Mult (Int (inv.Mult.0 ?s)) (Int (inv.Mult.1 ?s)))"),
+        rw!("Div"; "(Int ?s)" => "(Div (Int (inv.Div.0 ?s)) (Int (inv.Div.1 ?s)))"),
+        rw!("Abs"; "(Int ?s)" => "(Abs (Int (inv.Abs.0 ?s)))"),
+        rw!("Mod"; "(Int ?s)" => "(Mod (Int (inv.Mod.0 ?s)) (Int (inv.Mod.1 ?s)))"),
+        rw!("NegI"; "(Int ?s)" => "(NegI (Int (inv.NegI.0 ?s)))"),
+        rw!("NegB"; "(Bool ?s)" => "(NegB (Bool (inv.NegB.0 ?s)))"),
+        rw!("And"; "(Bool ?s)" => "(And (Bool (inv.And.0 ?s)) (Bool (inv.And.1 ?s)))"),
+        rw!("Or"; "(Bool ?s)" => "(Or (Bool (inv.Or.0 ?s)) (Bool (inv.Or.1 ?s)))"),
+        rw!("Xor"; "(Bool ?s)" => "(Xor (Bool (inv.Xor.0 ?s)) (Bool (inv.Xor.1 ?s)))"),
+        rw!("Implies"; "(Bool ?s)" => "(Implies (Bool (inv.Implies.0 ?s)) (Bool (inv.Implies.1 ?s)))"),
+        rw!("LexEq"; "(Bool ?s)" => "(LexEq (String (inv.LexEq.0 ?s)) (String (inv.LexEq.1 ?s)))"),
+        rw!("LexLt"; "(Bool ?s)" => "(LexLt (String (inv.LexLt.0 ?s)) (String (inv.LexLt.1 ?s)))"),
+        rw!("LexLeq"; "(Bool ?s)" => "(LexLeq (String (inv.LexLeq.0 ?s)) (String (inv.LexLeq.1 ?s)))"),
+        rw!("LexGeq"; "(Bool ?s)" => "(LexGeq (String (inv.LexGeq.0 ?s)) (String (inv.LexGeq.1 ?s)))"),
+        rw!("IsDigit"; "(Bool ?s)" => "(IsDigit (String (inv.IsDigit.0 ?s)))"),
+        rw!("ToCode"; "(Int ?s)" => "(ToCode (String (inv.ToCode.0 ?s)))"),
+        rw!("FromCode"; "(String ?s)" => "(FromCode (Int (inv.FromCode.0 ?s)))"),
     ]
 }
 
@@ -165,6 +319,12 @@ pub fn get_term(
     get_term_rec(fills, prgm, &ids, ids.len() - 1)
 }
 
+/// The program `prgm`, which must be complete
+pub(crate) fn to_expr(prgm: &RecExpr<SLIALang>) -> Term {
+    let ids: Vec<Id> = (0..prgm.as_ref().len()).map(Id::from).collect();
+    get_term_rec(&HashMap::new(), prgm, &ids, ids.len() - 1)
+}
+
 enum ArgsVariant<X, Y> {
     One(fn(X) -> Y),
     Two(fn(X, X) -> Y),
@@ -178,48 +338,64 @@ fn get_term_rec(
     ids: &Vec<Id>,
     i: usize,
 ) -> Term {
-    if fills.contains_key(&ids[i]) {
+    // the fill of a class stands for its hole, not for its other nodes
+    if matches!(prgm[i.into()], SLIALang::Hole(..)) && fills.contains_key(&ids[i]) {
         Ok(fills[&ids[i]].clone())
     } else {
-        let constructor = match prgm[i.into()].op.as_str() {
-            "Append" => Two(Append),
-            "StrLen" => One(StrLen),
-            "StrAt" => Two(StrAt),
-            "SubStr" => Three(SubStr),
-            "IsPre" => Two(IsPre),
-            "IsPost" => Two(IsPost),
-            "Contains" => Two(Contains),
-            "Index" => Three(Index),
-            "Replace" => Three(Replace),
-            "ReplaceAll" => Three(ReplaceAll),
-            "Leq" => Two(Leq),
-            "Geq" => Two(Geq),
-            "Eql" => Two(Eql),
-            "Add" => Two(Add),
-            "Min" => Two(Min),
-            "Mult" => Two(Mult),
-            "Div" => Two(Div),
-            "Abs" => One(Abs),
-            "Mod" => Two(Mod),
-            "NegI" => One(NegI),
-            "NegB" => One(NegB),
-            "And" => Two(And),
-            "Or" => Two(Or),
-            "LexEq" => Two(LexEq),
-            "LexLeq" => Two(LexLeq),
-            "LexGeq" => Two(LexGeq),
-            "StrToInt" => One(StrToInt),
-            "IntToStr" => One(IntToStr),
-            _ => {
+        let node = &prgm[i.into()];
+        let constructor = match node {
+            SLIALang::Append(_) => Two(Append),
+            SLIALang::StrLen(_) => One(StrLen),
+            SLIALang::StrAt(_) => Two(StrAt),
+            SLIALang::SubStr(_) => Three(SubStr),
+            SLIALang::IsPre(_) => Two(IsPre),
+            SLIALang::IsPost(_) => Two(IsPost),
+            SLIALang::Contains(_) => Two(Contains),
+            SLIALang::Index(_) => Three(Index),
+            SLIALang::Replace(_) => Three(Replace),
+            SLIALang::ReplaceAll(_) => Three(ReplaceAll),
+            SLIALang::Leq(_) => Two(Leq),
+            SLIALang::Geq(_) => Two(Geq),
+            SLIALang::Lt(_) => Two(Lt),
+            SLIALang::Gt(_) => Two(Gt),
+            SLIALang::Eql(_) => Two(Eql),
+            SLIALang::Add(_) => Two(Add),
+            SLIALang::Min(_) => Two(Min),
+            SLIALang::Mult(_) => Two(Mult),
+            SLIALang::Div(_) => Two(Div),
+            SLIALang::Abs(_) => One(Abs),
+            SLIALang::Mod(_) => Two(Mod),
+            SLIALang::NegI(_) => One(NegI),
+            SLIALang::NegB(_) => One(NegB),
+            SLIALang::And(_) => Two(And),
+            SLIALang::Or(_) => Two(Or),
+            SLIALang::Xor(_) => Two(Xor),
+            SLIALang::Implies(_) => Two(Implies),
+            SLIALang::LexEq(_) => Two(LexEq),
+            SLIALang::LexLt(_) => Two(LexLt),
+            SLIALang::LexLeq(_) => Two(LexLeq),
+            SLIALang::LexGeq(_) => Two(LexGeq),
+            SLIALang::StrToInt(_) => One(StrToInt),
+            SLIALang::IntToStr(_) => One(IntToStr),
+            SLIALang::IsDigit(_) => One(IsDigit),
+            SLIALang::ToCode(_) => One(ToCode),
+            SLIALang::FromCode(_) => One(FromCode),
+            SLIALang::Int(n) => return Ok(Expr::ConstInt(*n)),
+            SLIALang::Bool(b) => return Ok(Expr::ConstBool(*b)),
+            SLIALang::Str(s) => return Ok(Expr::ConstStr(s.0.clone())),
+            SLIALang::Var(x) => return Ok(Expr::Var(x.to_string())),
+            SLIALang::Hole(sort, _) => {
                 return Err(format!(
                     "not a complete program: unfilled hole with label: {}",
-                    prgm[i.into()].op.as_str()
-                )
-                .into())
+                    sort
+                ))
+            }
+            SLIALang::Inv(_, _) | SLIALang::RootSpec => {
+                return Err(format!("not a program: {}", node))
             }
         };
-        let subterms: Vec<Expr> = prgm[i.into()]
-            .children
+        let subterms: Vec<Expr> = node
+            .children()
             .iter()
             .map(|id| get_term_rec(fills, prgm, ids, usize::from(*id)))
             .collect::<Result<Vec<Expr>, String>>()?;
@@ -243,6 +419,7 @@ mod tests {
     use egg::Extractor;
 
     use super::*;
+    use crate::language::Eval;
 
     #[test]
     fn run_build_egraph() {
@@ -272,9 +449,9 @@ mod tests {
                 Expr::ConstStr("Ducati".into()),
             ),
         ]));
-        let components = HashMap::new();
-        let mut fills = HashMap::new();
-        let cost_function = EvalCostFn::new(&runner.egraph, &components, &mut fills);
+        let fills = fill_holes(&runner.egraph, &Bank::default());
+        let weights = Weights::default();
+        let cost_function = EvalCostFn::new(&runner.egraph, &fills, &weights);
         let extractor = Extractor::new(&runner.egraph, cost_function);
         let ((cost_a, cost_b, cost_c), best) = extractor.find_best(runner.roots[0]);
         println!(
@@ -282,4 +459,156 @@ mod tests {
             best, cost_a, cost_b, cost_c,
         );
     }
+
+    #[test]
+    fn inverse_rule_specs() {
+        let s = |s: &str| Expr::ConstStr(s.into());
+        // one round of the rules from a root of `sort`
+        let expand = |sort: &str, ios: Vec<(Expr, Expr)>| {
+            let start: RecExpr<SLIALang> = format!("({} root_spec)", sort).parse().unwrap();
+            let mut runner = Runner::default().with_expr(&start).with_iter_limit(1);
+            runner.egraph.set_analysis_data(0.into(), Examples(ios));
+            runner.egraph.rebuild();
+            runner.run(&grammar_rules()).egraph
+        };
+        let class = |egraph: &EGraph<SLIALang, Spec>, e: &str| {
+            egraph.lookup_expr(&e.parse().unwrap()).expect(e)
+        };
+
+        // the index of `str.at` has its own inverse, rather than the string's
+        // examples under an Int hole
+        let egraph = expand("String", vec![(s("ab"), s("b"))]);
+        let index = class(&egraph, "(inv.StrAt.1 root_spec)");
+        assert_eq!(egraph[index].data, Indeterminate);
+        let egraph = expand("String", vec![(s("ab"), s("c"))]);
+        let index = class(&egraph, "(inv.StrAt.1 root_spec)");
+        assert_eq!(egraph[index].data, Impossible);
+
+        // the arguments of string predicates are separate holes
+        let egraph = expand("Bool", vec![(s("ab"), Expr::ConstBool(true))]);
+        for op in ["IsPre", "IsPost", "Contains"] {
+            let a = class(&egraph, &format!("(String (inv.{}.0 root_spec))", op));
+            let b = class(&egraph, &format!("(String (inv.{}.1 root_spec))", op));
+            assert_ne!(a, b);
+            let spec = class(&egraph, &format!("(inv.{}.1 root_spec)", op));
+            assert_eq!(egraph[spec].data, Indeterminate);
+        }
+
+        // `abs` has a single argument
+        let egraph = expand("Int", vec![(s("ab"), Expr::ConstInt(2))]);
+        class(&egraph, "(Abs (Int (inv.Abs.0 root_spec)))");
+    }
+
+    #[test]
+    fn fold_constants() {
+        let mut egraph: EGraph<SLIALang, Spec> = EGraph::default();
+        let sum = egraph.add_expr(&"(Add 1 (Mult 2 3))".parse().unwrap());
+        egraph.rebuild();
+        assert_eq!(egraph[sum].data, Constant(Expr::ConstInt(7)));
+        assert_eq!(egraph.lookup(SLIALang::Int(7)), Some(egraph.find(sum)));
+
+        // a hole whose examples the constant does not satisfy
+        let root = egraph.add_expr(&"root_spec".parse().unwrap());
+        let ios = vec![(Expr::ConstStr("ab".into()), Expr::ConstInt(2))];
+        egraph.set_analysis_data(root, Examples(ios));
+        let hole = egraph.add(SLIALang::Hole(slia::Sort::Int, root));
+        egraph.union(hole, sum);
+        egraph.rebuild();
+        // only the folded alternatives are ruled out, and the hole is filled
+        assert_eq!(egraph[sum].data, Indeterminate);
+        let len = Expr::call(StrLen(Expr::Var("name".into())));
+        let fills = HashMap::from([(egraph.find(hole), len.clone())]);
+        let weights = Weights::default();
+        let cost_function = EvalCostFn::new(&egraph, &fills, &weights);
+        let ((unfillable, _, _), best) = Extractor::new(&egraph, cost_function).find_best(sum);
+        assert_eq!(unfillable, 0);
+        assert_eq!(get_term(&egraph, &fills, &best), Ok(len));
+
+        // in the real pipeline, constants reach the e-graph through fills
+        let s = |s: &str| Expr::ConstStr(s.into());
+        let ios = vec![(s("ab"), s("-")), (s("xyz"), s("-"))];
+        let mut runner = build_runner(Examples(ios.clone()));
+        let components = HashMap::from([("String".to_string(), vec![s("-")])]);
+        let inputs = ios.into_iter().map(|(i, _)| i).collect();
+        let bank = Bank::new(&components, "name", inputs);
+        let fills = fill_holes(&runner.egraph, &bank);
+        let fills = fold_fills(&mut runner.egraph, &fills);
+        let root = runner.egraph.find(runner.roots[0]);
+        assert_eq!(runner.egraph[root].data, Constant(s("-")));
+        assert_eq!(fills.get(&root), Some(&s("-")));
+        let literal = SLIALang::Str(slia::Str("-".into()));
+        assert_eq!(runner.egraph.lookup(literal), Some(root));
+    }
+
+    #[test]
+    fn fold_alternatives() {
+        let s = |s: &str| Expr::ConstStr(s.into());
+        let mut egraph: EGraph<SLIALang, Spec> = EGraph::default();
+        let root = egraph.add_expr(&"root_spec".parse().unwrap());
+        let ios = vec![(s("ab"), s("--")), (s("xyz"), s("--"))];
+        egraph.set_analysis_data(root, Examples(ios));
+        let mut add = |e: &str| egraph.add_expr(&e.parse().unwrap());
+        let hole = add("(String root_spec)");
+        let append =
+            add("(Append (String (inv.Append.0 root_spec)) (String (inv.Append.1 root_spec)))");
+        let replace = add(
+            "(Replace (String (inv.Replace.0 root_spec)) (String (inv.Replace.1 root_spec)) \
+             (String (inv.Replace.2 root_spec)))",
+        );
+        let [a, b] = [0, 1].map(|i| egraph[append].nodes[0].children()[i]);
+        let [c, d, e] = [0, 1, 2].map(|i| egraph[replace].nodes[0].children()[i]);
+        egraph.union(hole, append);
+        egraph.union(hole, replace);
+        egraph.rebuild();
+
+        // the Append alternative folds to the examples' output, and the
+        // Replace one to another constant, which alone is ruled out
+        let fills = HashMap::from([
+            (a, s("-")),
+            (b, s("-")),
+            (c, s("x")),
+            (d, s("x")),
+            (e, s("+")),
+        ]);
+        let fills = fold_fills(&mut egraph, &fills);
+        assert_ne!(egraph[hole].data, Impossible);
+        let weights = Weights::default();
+        let cost_function = EvalCostFn::new(&egraph, &fills, &weights);
+        let ((unfillable, holes, _), best) = Extractor::new(&egraph, cost_function).find_best(hole);
+        assert_eq!((unfillable, holes), (0, 0));
+        let program = get_term(&egraph, &fills, &best).unwrap();
+        assert_eq!(program.eval(&HashMap::new()), Ok(s("--")));
+
+        // a hole filled like its own children becomes its own child, and
+        // folding it must still end
+        let fills = HashMap::from([(egraph.find(hole), s("--")), (egraph.find(a), s("--"))]);
+        let fills = fold_fills(&mut egraph, &fills);
+        assert_eq!(egraph.find(a), egraph.find(hole));
+        assert_eq!(fills.get(&egraph.find(hole)), Some(&s("--")));
+    }
+
+    #[test]
+    fn merge_by_outputs() {
+        let name = || Expr::Var("name".into());
+        let inputs = vec![Expr::ConstStr("ab".into()), Expr::ConstStr("xyz".into())];
+        let bank = Bank::new(&HashMap::new(), "name", inputs.clone());
+
+        let mut egraph: EGraph<SLIALang, Spec> = EGraph::default();
+        let root = egraph.add_expr(&"root_spec".parse().unwrap());
+        let ios = inputs.iter().map(|i| (i.clone(), i.clone())).collect();
+        egraph.set_analysis_data(root, Examples(ios));
+        let a = egraph.add_expr(&"(String (inv.SubStr.0 root_spec))".parse().unwrap());
+        let b = egraph.add_expr(&"(String (inv.Append.0 root_spec))".parse().unwrap());
+        egraph.rebuild();
+
+        let whole = Expr::call(SubStr(
+            name(),
+            Expr::ConstInt(0),
+            Expr::call(StrLen(name())),
+        ));
+        let fills = HashMap::from([(a, whole), (b, name())]);
+        let fills = merge_equivalent(&mut egraph, &fills, &bank);
+        assert_eq!(egraph.find(a), egraph.find(b));
+        assert_eq!(fills, HashMap::from([(egraph.find(a), name())]));
+    }
 }
@@ -1,50 +1,282 @@
 use egg::Extractor;
-use std::{collections::HashMap, env, fs, time::Instant};
-use theremin::{build_runner, enumerate, get_term, language::Expr, sygus, EvalCostFn, Spec};
+use std::io::{self, BufReader};
+use std::time::{Duration, Instant};
+use std::{env, fs};
+use theremin::sygus::{Dialect, Function, Sort, Term};
+use theremin::{
+    apply::{self, Format, Program},
+    bank::Bank,
+    build_runner, codegen,
+    disambiguate::{most_informative, top_k},
+    enumerate::{Bound, Enumerated, Enumerator},
+    fill_holes, fold_fills, get_term,
+    language::Expr,
+    merge_equivalent, regex, search, simplify, sygus,
+    table::Table,
+    EvalCostFn, Spec,
+};
+
+const USAGE: &str = "Usage: theremin <path> (<depth> | --max-size <size>) \
+                     [--dialect standard|legacy] [--weights <solutions>] [--max-memory <MiB>] \
+                     [--merge-equivalent] [--budget <seconds>] [--disambiguate <inputs>] \
+                     [--input <column>] [--output <column>] [--write <path>] [--emit rust|python|sql]\n       \
+                     theremin apply <definition> [<inputs>]\n\
+                     A table (.csv, .tsv) gives a function of one input column, the only column \
+                     besides the output or the one picked with --input.";
 
 fn main() {
-    let args: Vec<String> = env::args().take(3).collect();
-    if args.len() != 3 {
-        println!("Usage: theremin <path> <depth>");
+    let mut args = env::args().skip(1);
+    let Some(path) = args.next() else {
+        println!("{}", USAGE);
+        return;
+    };
+    if path == "apply" {
+        apply(args);
         return;
     }
+    let mut bound = None;
+    let mut dialect = Dialect::default();
+    let mut corpus = None;
+    let mut max_memory: Option<usize> = None;
+    let mut merge = false;
+    let mut budget: Option<Duration> = None;
+    let mut pool = None;
+    let mut input = None;
+    let mut output = None;
+    let mut write = None;
+    let mut emit = None;
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), bound.is_none()) {
+            ("--dialect", _) => match args.next().as_deref() {
+                Some("standard") => dialect = Dialect::Standard,
+                Some("legacy") => dialect = Dialect::Legacy,
+                _ => {
+                    println!("{}", USAGE);
+                    return;
+                }
+            },
+            ("--weights", _) if corpus.is_none() => match args.next() {
+                Some(path) => corpus = Some(path),
+                None => {
+                    println!("{}", USAGE);
+                    return;
+                }
+            },
+            ("--max-memory", _) => {
+                let mib = args.next().and_then(|n| n.parse().ok());
+                max_memory = Some(mib.expect("memory cap should be a number of MiB"));
+            }
+            ("--merge-equivalent", _) => merge = true,
+            ("--disambiguate", _) if pool.is_none() => match args.next() {
+                Some(path) => pool = Some(path),
+                None => {
+                    println!("{}", USAGE);
+                    return;
+                }
+            },
+            ("--input", _) if input.is_none() => match args.next() {
+                Some(column) => input = Some(column),
+                None => {
+                    println!("{}", USAGE);
+                    return;
+                }
+            },
+            ("--output", _) if output.is_none() => match args.next() {
+                Some(column) => output = Some(column),
+                None => {
+                    println!("{}", USAGE);
+                    return;
+                }
+            },
+            ("--write", _) if write.is_none() => match args.next() {
+                Some(path) => write = Some(path),
+                None => {
+                    println!("{}", USAGE);
+                    return;
+                }
+            },
+            ("--emit", _) if emit.is_none() => match args.next().as_deref() {
+                Some("rust") => emit = Some(Language::Rust),
+                Some("python") => emit = Some(Language::Python),
+                Some("sql") => emit = Some(Language::Sql),
+                _ => {
+                    println!("{}", USAGE);
+                    return;
+                }
+            },
+            ("--budget", _) => {
+                let seconds = args.next().and_then(|n| n.parse().ok());
+                budget = Some(Duration::from_secs_f64(
+                    seconds.expect("budget should be a number of seconds"),
+                ));
+            }
+            ("--max-size", true) => {
+                let size = args.next().and_then(|n| n.parse().ok());
+                bound = Some(Bound::Size(size.expect("size should be a number")));
+            }
+            (depth, true) if !depth.starts_with("--") => {
+                bound = Some(Bound::Depth(
+                    depth.parse().expect("depth should be a number"),
+                ))
+            }
+            _ => {
+                println!("{}", USAGE);
+                return;
+            }
+        }
+    }
+    let Some(bound) = bound else {
+        println!("{}", USAGE);
+        return;
+    };
 
-    let depth = args[2].parse().expect("depth should be a number");
-    let file = fs::read_to_string(&args[1]).expect("cannot read file");
+    let file = fs::read_to_string(&path).expect("cannot read file");
 
-    let conjecture = sygus::parse_file(&file).expect("conjecture");
+    // a table of examples, with one column for the output and one for the
+    // input, stands for a conjecture over a default string grammar; synthesis
+    // takes a single parameter, so other columns are left out
+    let mut table = None;
+    let mut conjecture = match delimiter(&path) {
+        Some(delimiter) => {
+            let rows = Table::parse(&file, delimiter).expect("table");
+            let column = match &output {
+                Some(header) => rows.column(header).expect("no such output column"),
+                None => rows.headers.len().checked_sub(1).expect("no columns"),
+            };
+            let inputs: Vec<usize> = match &input {
+                Some(header) => vec![rows.column(header).expect("no such input column")],
+                None => (0..rows.headers.len()).filter(|&i| i != column).collect(),
+            };
+            if inputs.len() != 1 {
+                println!(
+                    "functions of one input column are supported, and the table has {} besides the output; \
+                     pick one with --input",
+                    inputs.len()
+                );
+                return;
+            }
+            let conjecture = rows.conjecture(&inputs, column).expect("conjecture");
+            table = Some((rows, delimiter, inputs, column));
+            conjecture
+        }
+        None => sygus::parse_file(&file).expect("conjecture"),
+    };
+    let mut write_back = |program: &Expr| {
+        if let (Some((rows, delimiter, inputs, column)), Some(write)) = (&mut table, &write) {
+            rows.fill(inputs, *column, program);
+            fs::write(write, rows.to_string(*delimiter)).expect("cannot write table");
+        }
+    };
+    if let Some(corpus) = corpus {
+        let corpus = fs::read_to_string(corpus).expect("cannot read solutions");
+        let solutions = sygus::parse_solutions(&corpus).expect("solutions");
+        for function in &mut conjecture.functions_to_synthesize {
+            function.grammar.learn_weights(&solutions);
+        }
+    }
 
     // Only synthesizes for 1 function
     let function = conjecture
         .functions_to_synthesize
-        .get(0)
+        .first()
         .expect("function to synthesize");
 
-    let now = Instant::now();
-    let bank = enumerate::bottom_up(&function.grammar, depth);
-    println!("Bottom up enumeration took {}", now.elapsed().as_secs_f64());
+    if function.return_sort == Sort::Identifier("RegLan".to_string()) {
+        let Bound::Depth(depth) = bound else {
+            println!("regular expressions are only enumerated by depth");
+            return;
+        };
+        let (positives, negatives) = conjecture
+            .regex_specification(&function.name)
+            .expect("specification");
+        let now = Instant::now();
+        let result = regex::synthesize(&function.grammar, &positives, &negatives, depth);
+        println!("Regex synthesis took {}", now.elapsed().as_secs_f64());
+        match result {
+            Some(regex) => println!("{}", define_fun(function, regex.to_term(dialect))),
+            None => println!("no regular expression found up to depth {}", depth),
+        }
+        return;
+    }
+
+    let specification = conjecture.specification().expect("specification");
+    let (parameter, _) = function.parameters.first().expect("parameter");
+    let inputs: Vec<Expr> = specification.iter().map(|(i, _)| i.clone()).collect();
 
     let now = Instant::now();
-    let components: HashMap<String, Vec<Expr>> = bank
-        .iter()
-        .map(|(sort, terms)| {
-            let exprs = terms.iter().map(|t| t.try_into().expect("expr")).collect();
-            (sort.to_owned(), exprs)
-        })
-        .collect();
-    println!("Component conversion took {}", now.elapsed().as_secs_f64());
+    let mut enumerator =
+        Enumerator::new(&function.grammar, bound).with_inputs(parameter, inputs.clone());
+    if let Some(mib) = max_memory {
+        enumerator = enumerator.with_max_memory(mib << 20);
+    }
+    // components come evaluated, so the bank is filled as they stream in
+    let mut bank = Bank::with_inputs(parameter, inputs);
+    if pool.is_some() {
+        bank = bank.with_alternatives(4);
+    }
 
-    let specification = conjecture.specification().expect("specification");
+    if let Some(budget) = budget {
+        let weights = function.grammar.op_weights();
+        let result = search::bidirectional(enumerator, bank, specification, &weights, budget);
+        println!("Bidirectional search took {}", now.elapsed().as_secs_f64());
+        match result {
+            Ok(program) => {
+                let program = simplify::simplify(&program);
+                println!("{}", define_fun(function, program.to_term(dialect)));
+                if let Some(language) = emit {
+                    println!("{}", generate(function, &program, language));
+                }
+                write_back(&program);
+            }
+            Err(e) => println!("{}", e),
+        }
+        return;
+    }
+
+    while let Some(Enumerated {
+        sort, id, outputs, ..
+    }) = enumerator.next()
+    {
+        // regular expressions have no outputs, as they are not programs
+        if let Some(outputs) = outputs {
+            let arena = enumerator.arena();
+            bank.insert_with(&sort.to_string(), arena.size(id), outputs, || {
+                (&arena.term(id)).try_into().expect("expr")
+            });
+        }
+    }
+    if enumerator.truncated() {
+        println!("Enumeration stopped at the memory cap");
+    }
+    println!(
+        "Bottom up enumeration and evaluation took {}",
+        now.elapsed().as_secs_f64()
+    );
 
     let now = Instant::now();
-    let runner = build_runner(Spec::Examples(specification));
+    let mut runner = build_runner(Spec::Examples(specification.clone()));
     println!(
         "Egraph runner construction took {}",
         now.elapsed().as_secs_f64()
     );
-    let mut fills = HashMap::new();
+    let now = Instant::now();
+    let fills = fill_holes(&runner.egraph, &bank);
+    let mut fills = fold_fills(&mut runner.egraph, &fills);
+    println!("Hole filling took {}", now.elapsed().as_secs_f64());
+    if merge {
+        let now = Instant::now();
+        let classes = runner.egraph.number_of_classes();
+        fills = merge_equivalent(&mut runner.egraph, &fills, &bank);
+        println!(
+            "Merging equivalent classes ({} to {}) took {}",
+            classes,
+            runner.egraph.number_of_classes(),
+            now.elapsed().as_secs_f64()
+        );
+    }
 
-    let cost_function = EvalCostFn::new(&runner.egraph, &components, &mut fills);
+    let weights = function.grammar.op_weights();
+    let cost_function = EvalCostFn::new(&runner.egraph, &fills, &weights);
 
     let now = Instant::now();
     let ((cost_a, cost_b, cost_c), best) =
@@ -57,5 +289,128 @@ fn main() {
         best, cost_a, cost_b, cost_c,
     );
     println!("{:?}", runner.egraph.lookup_expr_ids(&best));
-    println!("{:#?}", get_term(&runner.egraph, &fills, &best));
+    match get_term(&runner.egraph, &fills, &best) {
+        Ok(program) => {
+            let program = simplify::simplify(&program);
+            println!("{}", define_fun(function, program.to_term(dialect)));
+            if let Some(language) = emit {
+                println!("{}", generate(function, &program, language));
+            }
+            write_back(&program);
+        }
+        Err(e) => println!("{}", e),
+    }
+
+    // one unlabeled input per line, to tell the best programs apart
+    if let Some(pool) = pool {
+        let pool: Vec<Expr> = fs::read_to_string(pool)
+            .expect("cannot read inputs")
+            .lines()
+            .map(|line| Expr::ConstStr(line.to_owned()))
+            .collect();
+        let candidates: Vec<Expr> =
+            top_k(&runner.egraph, &fills, &bank, &weights, runner.roots[0], 10)
+                .into_iter()
+                .filter(|e| bank.satisfies(e, &specification))
+                .collect();
+        println!("{} candidates fit the examples:", candidates.len());
+        for candidate in &candidates {
+            println!("  {}", candidate.to_term(dialect));
+        }
+        match most_informative(&candidates, parameter, &pool) {
+            Some((input, groups)) => println!(
+                "Most informative input: {} ({} distinct outputs)",
+                input.to_term(dialect),
+                groups
+            ),
+            None => println!("The candidates agree on every input"),
+        }
+    }
+}
+
+/// Runs the first `define-fun` of a file on every row of the inputs, or of
+/// standard input, writing the results to standard output
+fn apply(mut args: impl Iterator<Item = String>) {
+    let (Some(definition), inputs, None) = (args.next(), args.next(), args.next()) else {
+        println!("{}", USAGE);
+        return;
+    };
+    let file = fs::read_to_string(definition).expect("cannot read definition");
+    let definitions = sygus::parse_definitions(&file).expect("definition");
+    let definition = definitions.first().expect("no define-fun");
+    let program = Program::from_definition(definition).expect("program");
+
+    let stdout = io::stdout().lock();
+    let result = match inputs {
+        Some(path) => {
+            let file = fs::File::open(&path).expect("cannot read inputs");
+            apply::run(
+                &program,
+                Format::of_path(&path),
+                BufReader::new(file),
+                stdout,
+            )
+        }
+        None => apply::run(&program, Format::Lines, io::stdin().lock(), stdout),
+    };
+    let (rows, errors) = result.expect("cannot apply program");
+    eprintln!(
+        "Applied {} to {} rows, {} failed",
+        definition.name, rows, errors
+    );
+}
+
+/// The field delimiter of a table of examples, by its extension
+fn delimiter(path: &str) -> Option<char> {
+    match path.rsplit_once('.')?.1 {
+        "csv" => Some(','),
+        "tsv" => Some('\t'),
+        _ => None,
+    }
+}
+
+/// The languages a synthesized program can be exported to
+#[derive(Clone, Copy)]
+enum Language {
+    Rust,
+    Python,
+    Sql,
+}
+
+/// `program` as a function of `language`
+fn generate(function: &Function, program: &Expr, language: Language) -> String {
+    let result = match language {
+        Language::Rust => codegen::rust(&function.name, &function.parameters, program),
+        Language::Python => codegen::python(&function.parameters, program).map(|body| {
+            let parameters: Vec<String> = function
+                .parameters
+                .iter()
+                .map(|(name, _)| codegen::python_name(name))
+                .collect();
+            let name = codegen::python_name(&function.name);
+            format!(
+                "def {}({}):\n    return {}",
+                name,
+                parameters.join(", "),
+                body
+            )
+        }),
+        Language::Sql => codegen::sql(&function.parameters, program),
+    };
+    result.unwrap_or_else(|e| format!("cannot export the program: {}", e))
+}
+
+fn define_fun(function: &Function, body: Term) -> String {
+    let parameters: Vec<String> = function
+        .parameters
+        .iter()
+        .map(|(name, sort)| format!("({} {})", name, sort))
+        .collect();
+    format!(
+        "(define-fun {} ({}) {} {})",
+        function.name,
+        parameters.join(" "),
+        function.return_sort,
+        body
+    )
 }
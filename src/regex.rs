@@ -0,0 +1,169 @@
+use crate::language::{RegLang, Translate};
+use crate::sygus::{BFTerm, GTerm, Grammar, Literal, Sort, Term};
+use itertools::Itertools;
+use std::collections::{HashMap, HashSet};
+
+/// Searches the `RegLan` productions of `grammar` bottom-up for a regular
+/// expression accepting every positive and no negative example.
+///
+/// Candidates are identified by which substrings of the examples they accept,
+/// since that decides membership of every larger expression built from them.
+/// Only the first candidate of each such signature is kept per nonterminal,
+/// as another nonterminal may need one of its own to build on. Candidates that
+/// accept a negative example are never returned, but remain available as
+/// subterms.
+pub fn synthesize(
+    grammar: &Grammar,
+    positives: &[String],
+    negatives: &[String],
+    depth: usize,
+) -> Option<RegLang> {
+    let mut substrings: Vec<String> = Vec::new();
+    for example in positives.iter().chain(negatives) {
+        let chars: Vec<char> = example.chars().collect();
+        for i in 0..=chars.len() {
+            for j in i..=chars.len() {
+                substrings.push(chars[i..j].iter().collect());
+            }
+        }
+    }
+    substrings.sort();
+    substrings.dedup();
+    let index = |s: &String| substrings.binary_search(s).unwrap();
+    let positive: Vec<usize> = positives.iter().map(index).collect();
+    let negative: Vec<usize> = negatives.iter().map(index).collect();
+
+    let regex_sort = Sort::Identifier("RegLan".to_string());
+    let strings = string_constants(grammar, positives, &regex_sort);
+
+    let mut levels: Vec<HashMap<&str, Vec<Term>>> = Vec::new();
+    let mut seen: HashSet<(&str, Vec<bool>)> = HashSet::new();
+    for d in 0..depth {
+        let mut level: HashMap<&str, Vec<Term>> = HashMap::new();
+        for (name, _, rhs) in grammar.rules.iter().filter(|(_, s, _)| *s == regex_sort) {
+            for term in candidates(rhs, d, &levels, &strings) {
+                let Ok(regex) = RegLang::try_from(&term) else {
+                    continue;
+                };
+                let Ok(re) = regex.translate(&HashMap::new()) else {
+                    continue;
+                };
+                let signature: Vec<bool> = substrings.iter().map(|s| re.matches(s)).collect();
+                if !seen.insert((name.as_str(), signature.clone())) {
+                    continue;
+                }
+                if positive.iter().all(|i| signature[*i]) && !negative.iter().any(|i| signature[*i])
+                {
+                    return Some(regex);
+                }
+                level.entry(name.as_str()).or_default().push(term);
+            }
+        }
+        levels.push(level);
+    }
+    None
+}
+
+/// The string arguments available to regular expressions: the literals of each
+/// string nonterminal, and the characters of the positive examples wherever
+/// the grammar allows `(Constant String)`.
+fn string_constants(
+    grammar: &Grammar,
+    positives: &[String],
+    regex_sort: &Sort,
+) -> HashMap<String, Vec<Term>> {
+    let characters: Vec<Term> = positives
+        .iter()
+        .flat_map(|s| s.chars())
+        .sorted()
+        .dedup()
+        .map(|c| Term::Literal(Literal::String(c.to_string())))
+        .collect();
+    let mut strings: HashMap<String, Vec<Term>> = HashMap::new();
+    for (name, _, rhs) in grammar.rules.iter().filter(|(_, s, _)| s != regex_sort) {
+        let constants = strings.entry(name.to_owned()).or_default();
+        for g_term in rhs {
+            match g_term {
+                GTerm::BFTerm(BFTerm::Literal(lit)) => constants.push(Term::Literal(lit.clone())),
+                GTerm::Constant(Sort::Identifier(sort)) if sort == "String" => {
+                    constants.extend(characters.iter().cloned())
+                }
+                _ => {} // do nothing
+            }
+        }
+    }
+    strings
+}
+
+fn candidates(
+    g_terms: &Vec<GTerm>,
+    depth: usize,
+    levels: &[HashMap<&str, Vec<Term>>],
+    strings: &HashMap<String, Vec<Term>>,
+) -> Vec<Term> {
+    let mut terms: Vec<Term> = Vec::new();
+    for g_term in g_terms {
+        match g_term {
+            GTerm::BFTerm(BFTerm::Application(name, holes)) if depth > 0 => {
+                let fills = holes.iter().map(|hole| match hole {
+                    BFTerm::Identifier(nt) if strings.contains_key(nt) => strings[nt].clone(),
+                    BFTerm::Identifier(nt) => levels
+                        .iter()
+                        .flat_map(|level| level.get(nt.as_str()).into_iter().flatten())
+                        .cloned()
+                        .collect(),
+                    BFTerm::Literal(lit) => vec![Term::Literal(lit.clone())],
+                    BFTerm::Application(_, _) => Vec::new(),
+                });
+                for fill in fills.multi_cartesian_product() {
+                    terms.push(Term::Application(name.to_owned(), fill));
+                }
+            }
+            GTerm::BFTerm(BFTerm::Identifier(name)) if depth == 0 => {
+                terms.push(Term::Identifier(name.to_owned()));
+            }
+            _ => {} // do nothing
+        }
+    }
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sygus;
+
+    #[test]
+    fn learn_zip_code() {
+        let conjecture =
+            sygus::parse_file(include_str!("../assets/benchmarks/zip-code.sl")).expect("parse");
+        let function = &conjecture.functions_to_synthesize[0];
+        let (positives, negatives) = conjecture
+            .regex_specification(&function.name)
+            .expect("specification");
+        let regex = synthesize(&function.grammar, &positives, &negatives, 3).expect("solution");
+
+        let re = regex.translate(&HashMap::new()).unwrap();
+        assert!(positives.iter().all(|s| re.matches(s)));
+        assert!(!negatives.iter().any(|s| re.matches(s)));
+
+        // the same signature under two nonterminals is kept for each
+        let conjecture = sygus::parse_file(
+            r#"
+            (synth-fun f () RegLan
+                ((Start RegLan) (A RegLan) (B RegLan))
+                ((Start RegLan ((re.++ A B)))
+                (A RegLan ((str.to_re "a")))
+                (B RegLan ((str.to_re "a")))))
+            (constraint (str.in_re "aa" (f)))
+            (constraint (not (str.in_re "a" (f))))
+            "#,
+        )
+        .expect("parse");
+        let function = &conjecture.functions_to_synthesize[0];
+        let (positives, negatives) = conjecture
+            .regex_specification(&function.name)
+            .expect("specification");
+        assert!(synthesize(&function.grammar, &positives, &negatives, 3).is_some());
+    }
+}
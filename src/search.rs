@@ -0,0 +1,155 @@
+use crate::bank::Bank;
+use crate::enumerate::{Enumerated, Enumerator};
+use crate::language::{Expr, Term};
+use crate::slia::{SLIALang, Weights};
+use crate::{fill_holes, fold_fills, get_term, grammar_rules, EvalCostFn, Spec};
+use egg::{Applier, EGraph, Extractor, Id, PatternAst, Rewrite, Runner, Subst};
+use egg::{Symbol, Var};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Applies a grammar rule only to holes the bank has not filled, so that
+/// top-down expansion stops where bottom-up enumeration already reached.
+struct UnlessFilled {
+    applier: Arc<dyn Applier<SLIALang, Spec> + Sync + Send>,
+    filled: Arc<HashSet<Id>>,
+}
+
+impl Applier<SLIALang, Spec> for UnlessFilled {
+    fn apply_one(
+        &self,
+        egraph: &mut EGraph<SLIALang, Spec>,
+        eclass: Id,
+        subst: &Subst,
+        searcher_ast: Option<&PatternAst<SLIALang>>,
+        rule_name: Symbol,
+    ) -> Vec<Id> {
+        if self.filled.contains(&egraph.find(eclass)) {
+            return Vec::new();
+        }
+        self.applier
+            .apply_one(egraph, eclass, subst, searcher_ast, rule_name)
+    }
+
+    fn vars(&self) -> Vec<Var> {
+        self.applier.vars()
+    }
+}
+
+/// Searches from both ends within `budget`: one level of `enumerator` into
+/// `bank`, then one round of inverse expansion of the holes it cannot fill,
+/// until the extracted program has no holes left. Either side stops on its
+/// own once exhausted.
+///
+/// The enumerator must evaluate its terms on the inputs of `bank`, which are
+/// those of `examples`. Without a complete program in time, this returns
+/// the reason the best one found is not.
+pub fn bidirectional(
+    enumerator: Enumerator,
+    mut bank: Bank,
+    examples: Vec<(Expr, Expr)>,
+    weights: &Weights,
+    budget: Duration,
+) -> Term {
+    let deadline = Instant::now() + budget;
+    // a level of a large grammar may take longer than the whole budget
+    let mut enumerator = enumerator.with_deadline(deadline);
+    let mut egraph: EGraph<SLIALang, Spec> = EGraph::default();
+    let root = egraph.add_expr(&"(String root_spec)".parse().unwrap());
+    let spec = egraph.lookup_expr(&"root_spec".parse().unwrap()).unwrap();
+    egraph.set_analysis_data(spec, Spec::Examples(examples));
+    egraph.rebuild();
+
+    let mut enumerated = false;
+    let mut saturated = false;
+    loop {
+        if !enumerated {
+            let mut level = None;
+            enumerated = true;
+            while let Some(Enumerated {
+                sort, id, outputs, ..
+            }) = enumerator.next()
+            {
+                if let Some(outputs) = outputs {
+                    let arena = enumerator.arena();
+                    bank.insert_with(&sort.to_string(), arena.size(id), outputs, || {
+                        (&arena.term(id)).try_into().expect("expr")
+                    });
+                }
+                // one level per round, which ends with the first term of the next
+                if (level.is_some() && enumerator.level() != level) || Instant::now() > deadline {
+                    enumerated = false;
+                    break;
+                }
+                level = enumerator.level();
+            }
+        }
+
+        let fills = fill_holes(&egraph, &bank);
+        let fills = fold_fills(&mut egraph, &fills);
+        let cost_function = EvalCostFn::new(&egraph, &fills, weights);
+        let ((unfillable, holes, _), best) = Extractor::new(&egraph, cost_function).find_best(root);
+        let done = enumerated && saturated;
+        if (unfillable, holes) == (0, 0) || done || Instant::now() > deadline {
+            return get_term(&egraph, &fills, &best);
+        }
+
+        if !saturated {
+            let filled = Arc::new(fills.into_keys().collect::<HashSet<Id>>());
+            let rules: Vec<Rewrite<SLIALang, Spec>> = grammar_rules()
+                .into_iter()
+                .map(|mut rule| {
+                    rule.applier = Arc::new(UnlessFilled {
+                        applier: rule.applier,
+                        filled: filled.clone(),
+                    });
+                    rule
+                })
+                .collect();
+            let runner = Runner::default()
+                .with_egraph(egraph)
+                .with_iter_limit(1)
+                .with_time_limit(deadline.saturating_duration_since(Instant::now()))
+                .run(&rules);
+            // anything but the iteration limit means there is nothing to gain
+            saturated = !matches!(runner.stop_reason, Some(egg::StopReason::IterationLimit(_)));
+            egraph = runner.egraph;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enumerate::Bound;
+    use crate::sygus;
+
+    #[test]
+    fn meet_in_the_middle() {
+        let conjecture =
+            sygus::parse_file(include_str!("../assets/benchmarks/bikes.sl")).expect("parse");
+        let function = &conjecture.functions_to_synthesize[0];
+        let examples = conjecture.specification().expect("specification");
+        let inputs: Vec<Expr> = examples.iter().map(|(i, _)| i.clone()).collect();
+
+        let enumerator =
+            Enumerator::new(&function.grammar, Bound::Size(5)).with_inputs("name", inputs.clone());
+        let bank = Bank::with_inputs("name", inputs);
+        let weights = Weights::default();
+        let program = bidirectional(
+            enumerator,
+            bank,
+            examples,
+            &weights,
+            Duration::from_secs(60),
+        );
+        assert_eq!(
+            program
+                .expect("program")
+                .to_term(sygus::Dialect::Standard)
+                .to_string(),
+            "(str.substr name 0 (- (str.len name) 3))"
+        );
+    }
+}
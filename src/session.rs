@@ -0,0 +1,176 @@
+use crate::bank::Bank;
+use crate::enumerate::{Enumerated, Enumerator};
+use crate::language::{Eval, Expr, Term};
+use crate::slia::{SLIALang, Weights};
+use crate::{build_runner, get_term, inverse, simplify, EvalCostFn, Spec};
+use egg::{EGraph, Extractor, Id};
+use std::collections::HashMap;
+
+/// Synthesis from examples that arrive one at a time. The components and
+/// the e-graph are built once; each new example only evaluates the
+/// components on its input, pushes the specification down the existing
+/// `inv` nodes and re-checks the fills.
+pub struct SynthesisSession {
+    parameter: String,
+    examples: Vec<(Expr, Expr)>,
+    /// Every component that evaluates on all inputs so far, with its outputs
+    components: HashMap<String, Vec<(Expr, Vec<Expr>)>>,
+    bank: Bank,
+    egraph: EGraph<SLIALang, Spec>,
+    root: Id,
+    fills: HashMap<Id, Expr>,
+    weights: Weights,
+}
+
+impl SynthesisSession {
+    /// Starts a session without examples, over every term of `enumerator`
+    /// as a program of `parameter`
+    pub fn new(mut enumerator: Enumerator, parameter: &str, weights: Weights) -> Self {
+        let mut components: HashMap<String, Vec<(Expr, Vec<Expr>)>> = HashMap::new();
+        while let Some(Enumerated { sort, id, .. }) = enumerator.next() {
+            let sort = sort.to_string();
+            // regular expressions are not programs on their own
+            if sort == "RegLan" {
+                continue;
+            }
+            if let Ok(expr) = (&enumerator.arena().term(id)).try_into() {
+                components.entry(sort).or_default().push((expr, Vec::new()));
+            }
+        }
+        let runner = build_runner(Spec::Examples(Vec::new()));
+        SynthesisSession {
+            parameter: parameter.to_owned(),
+            examples: Vec::new(),
+            components,
+            bank: Bank::with_inputs(parameter, Vec::new()),
+            root: runner.roots[0],
+            egraph: runner.egraph,
+            fills: HashMap::new(),
+            weights,
+        }
+    }
+
+    pub fn examples(&self) -> &[(Expr, Expr)] {
+        &self.examples
+    }
+
+    /// Adds the example mapping `input` to `output`, and returns the best
+    /// program for all examples so far
+    pub fn add_example(&mut self, input: Expr, output: Expr) -> Term {
+        self.examples.push((input.clone(), output));
+
+        // only the new input needs evaluating
+        let env = HashMap::from([(self.parameter.clone(), input.clone())]);
+        self.bank.add_input(input);
+        for (sort, components) in &mut self.components {
+            components.retain_mut(|(e, outputs)| match e.clone().eval(&env) {
+                Ok(out) => {
+                    outputs.push(out);
+                    true
+                }
+                Err(_) => false,
+            });
+            // the bank kept one component per output vector, and those it
+            // passed over may have outputs of their own now. Only the ones
+            // it keeps are cloned.
+            for (e, outputs) in components.iter() {
+                self.bank
+                    .insert_with(sort, e.size(), outputs.clone(), || e.clone());
+            }
+        }
+
+        self.propagate();
+        self.refill();
+        self.best()
+    }
+
+    /// Recomputes the specification of every `inv` class from its parent's,
+    /// starting from the examples at the root
+    fn propagate(&mut self) {
+        let spec = self.egraph.lookup(SLIALang::RootSpec).expect("root_spec");
+        self.egraph[spec].data = Spec::Examples(self.examples.clone());
+        let invs: Vec<(Id, SLIALang)> = self
+            .egraph
+            .classes()
+            .flat_map(|class| class.nodes.iter().map(move |node| (class.id, node.clone())))
+            .filter(|(_, node)| matches!(node, SLIALang::Inv(..)))
+            .collect();
+        // a class is recomputed once its parent is, so this stops within
+        // as many passes as the e-graph is deep
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (id, node) in &invs {
+                let SLIALang::Inv(tag, parent) = node else {
+                    continue;
+                };
+                let spec = inverse::invert(&self.egraph[*parent].data, *tag);
+                if self.egraph[*id].data != spec {
+                    self.egraph[*id].data = spec;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    /// Keeps each fill that still satisfies its hole, and fills the others
+    /// from the bank again
+    fn refill(&mut self) {
+        let mut fills = HashMap::new();
+        for class in self.egraph.classes() {
+            let holes = class.nodes.iter().filter_map(|node| match node {
+                SLIALang::Hole(sort, spec) => match &self.egraph[*spec].data {
+                    Spec::Examples(ios) => Some((sort, ios)),
+                    _ => None,
+                },
+                _ => None,
+            });
+            let fill = holes
+                .filter_map(|(sort, ios)| match self.fills.get(&class.id) {
+                    Some(cached) if self.bank.satisfies(cached, ios) => Some(cached),
+                    _ => self.bank.fill(&sort.to_string(), ios),
+                })
+                .min_by_key(|e| e.size());
+            if let Some(e) = fill {
+                fills.insert(class.id, e.clone());
+            }
+        }
+        self.fills = fills;
+    }
+
+    /// The best program for the examples so far, simplified
+    pub fn best(&self) -> Term {
+        let cost_function = EvalCostFn::new(&self.egraph, &self.fills, &self.weights);
+        let (_, best) = Extractor::new(&self.egraph, cost_function).find_best(self.root);
+        get_term(&self.egraph, &self.fills, &best).map(|program| simplify::simplify(&program))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enumerate::Bound;
+    use crate::sygus;
+
+    #[test]
+    fn add_examples_one_at_a_time() {
+        let conjecture =
+            sygus::parse_file(include_str!("../assets/benchmarks/bikes.sl")).expect("parse");
+        let grammar = &conjecture.functions_to_synthesize[0].grammar;
+        let enumerator = Enumerator::new(grammar, Bound::Size(5));
+        let mut session = SynthesisSession::new(enumerator, "name", Weights::default());
+
+        let mut program = Err(String::new());
+        for (input, output) in conjecture.specification().expect("specification") {
+            program = session.add_example(input, output);
+        }
+        assert_eq!(session.examples().len(), 6);
+        assert_eq!(
+            program
+                .expect("program")
+                .to_term(sygus::Dialect::Standard)
+                .to_string(),
+            "(str.substr name 0 (- (str.len name) 3))"
+        );
+    }
+}
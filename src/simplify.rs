@@ -0,0 +1,206 @@
+use crate::language::{Eval, Expr};
+use crate::slia::{Op, SLIALang, Str};
+use egg::{rewrite as rw, Analysis, AstSize, DidMerge, EGraph, Extractor, FromOp, Id};
+use egg::{Language, RecExpr, Rewrite, Runner, Subst, Symbol, Var};
+use std::collections::{HashMap, HashSet};
+
+/// Folds constant subterms while simplifying: each class that evaluates to a
+/// constant also holds its literal.
+#[derive(Default)]
+pub struct ConstantFold;
+
+impl Analysis<SLIALang> for ConstantFold {
+    type Data = Option<Expr>;
+
+    fn merge(&mut self, to: &mut Self::Data, from: Self::Data) -> DidMerge {
+        // equal classes have equal values, so either is as good
+        egg::merge_option(to, from, |_, _| DidMerge(false, false))
+    }
+
+    fn make(egraph: &EGraph<SLIALang, Self>, enode: &SLIALang) -> Self::Data {
+        fold(enode, |id| egraph[id].data.clone())
+    }
+
+    fn modify(egraph: &mut EGraph<SLIALang, Self>, id: Id) {
+        if let Some(literal) = egraph[id].data.as_ref().and_then(literal) {
+            let added = egraph.add(literal);
+            egraph.union(id, added);
+        }
+    }
+}
+
+/// The value of `enode` if it is a literal, or an operator whose children all
+/// have a `constant` value. Operators that fail on their arguments have none.
+pub fn fold(enode: &SLIALang, constant: impl Fn(Id) -> Option<Expr>) -> Option<Expr> {
+    match enode {
+        SLIALang::Int(n) => Some(Expr::ConstInt(*n)),
+        SLIALang::Bool(b) => Some(Expr::ConstBool(*b)),
+        SLIALang::Str(s) => Some(Expr::ConstStr(s.0.clone())),
+        _ => {
+            Op::of_node(enode)?;
+            // rebuild the node over literal children, and evaluate it
+            let mut expr = RecExpr::default();
+            let mut children = HashMap::new();
+            for child in enode.children() {
+                let id = expr.add(literal(&constant(*child)?)?);
+                children.insert(*child, id);
+            }
+            expr.add(enode.clone().map_children(|child| children[&child]));
+            crate::to_expr(&expr).ok()?.eval(&HashMap::new()).ok()
+        }
+    }
+}
+
+/// The literal node of a constant
+pub(crate) fn literal(e: &Expr) -> Option<SLIALang> {
+    match e {
+        Expr::ConstInt(n) => Some(SLIALang::Int(*n)),
+        Expr::ConstBool(b) => Some(SLIALang::Bool(*b)),
+        Expr::ConstStr(s) => Some(SLIALang::Str(Str(s.clone()))),
+        _ => None,
+    }
+}
+
+/// Adds `e` to `expr`, unless it has a conditional or a regular expression,
+/// which the e-graph language cannot express.
+fn add_expr(e: &Expr, expr: &mut RecExpr<SLIALang>) -> Option<Id> {
+    let node = match e {
+        Expr::Var(x) => SLIALang::Var(Symbol::from(x)),
+        Expr::Call(f) => {
+            let op = Op::of_func(f)?;
+            let args: Option<Vec<Id>> = f.args().into_iter().map(|a| add_expr(a, expr)).collect();
+            SLIALang::from_op(&op.to_string(), args?).ok()?
+        }
+        Expr::If(..) => return None,
+        _ => literal(e)?,
+    };
+    Some(expr.add(node))
+}
+
+/// Identities of the string and integer operators, under SMT-LIB semantics
+pub fn rules() -> Vec<Rewrite<SLIALang, ConstantFold>> {
+    let mut rules = vec![
+        rw!("append-empty-right"; "(Append ?x ?e)" => "?x" if is_empty("?e")),
+        rw!("append-empty-left"; "(Append ?e ?x)" => "?x" if is_empty("?e")),
+        rw!("substr-whole"; "(SubStr ?x 0 (StrLen ?x))" => "?x"),
+        rw!("replace-empty"; "(Replace ?x ?e ?y)" => "(Append ?y ?x)" if is_empty("?e")),
+        rw!("replace-same"; "(Replace ?x ?y ?y)" => "?x" if total("?y")),
+        rw!("replaceall-same"; "(ReplaceAll ?x ?y ?y)" => "?x" if total("?y")),
+        rw!("strlen-append"; "(StrLen (Append ?x ?y))" => "(Add (StrLen ?x) (StrLen ?y))"),
+        rw!("add-zero"; "(Add ?a 0)" => "?a"),
+        rw!("add-comm"; "(Add ?a ?b)" => "(Add ?b ?a)"),
+        rw!("add-assoc"; "(Add (Add ?a ?b) ?c)" => "(Add ?a (Add ?b ?c))"),
+        rw!("sub-zero"; "(Min ?a 0)" => "?a"),
+        rw!("sub-self"; "(Min ?a ?a)" => "0" if total("?a")),
+        rw!("sub-add"; "(Min (Add ?a ?b) ?b)" => "?a"),
+        rw!("add-sub"; "(Add (Min ?a ?b) ?b)" => "?a"),
+        rw!("mult-one"; "(Mult ?a 1)" => "?a"),
+        rw!("mult-zero"; "(Mult ?a 0)" => "0" if total("?a")),
+        rw!("mult-comm"; "(Mult ?a ?b)" => "(Mult ?b ?a)"),
+        rw!("div-one"; "(Div ?a 1)" => "?a"),
+        rw!("neg-neg"; "(NegI (NegI ?a))" => "?a"),
+        rw!("abs-abs"; "(Abs (Abs ?a))" => "(Abs ?a)"),
+        rw!("not-not"; "(NegB (NegB ?a))" => "?a"),
+        rw!("and-true"; "(And ?a true)" => "?a"),
+        rw!("or-false"; "(Or ?a false)" => "?a"),
+    ];
+    rules
+        .extend(rw!("append-assoc"; "(Append (Append ?x ?y) ?z)" <=> "(Append ?x (Append ?y ?z))"));
+    rules
+}
+
+/// Whether `var` is the empty string. Patterns cannot hold string literals,
+/// as the s-expression parser drops their quotes.
+fn is_empty(var: &str) -> impl Fn(&mut EGraph<SLIALang, ConstantFold>, Id, &Subst) -> bool {
+    let var: Var = var.parse().unwrap();
+    move |egraph, _, subst| egraph[subst[var]].data == Some(Expr::ConstStr(String::new()))
+}
+
+/// Whether `var` never fails, so that a rule may drop it: it is a constant, or
+/// it has a term of variables and operators that are not
+/// [partial](Op::partial).
+fn total(var: &str) -> impl Fn(&mut EGraph<SLIALang, ConstantFold>, Id, &Subst) -> bool {
+    let var: Var = var.parse().unwrap();
+    move |egraph, _, subst| never_fails(egraph, subst[var], &mut HashSet::new())
+}
+
+/// Whether class `id` has a term that never fails. Classes on the path are
+/// `seen`, so that cycles count as failing.
+fn never_fails(egraph: &EGraph<SLIALang, ConstantFold>, id: Id, seen: &mut HashSet<Id>) -> bool {
+    let id = egraph.find(id);
+    if egraph[id].data.is_some() {
+        return true;
+    }
+    if !seen.insert(id) {
+        return false;
+    }
+    let total = egraph[id].nodes.iter().any(|node| match node {
+        SLIALang::Var(_) => true,
+        _ => {
+            Op::of_node(node).is_some_and(|op| !op.partial())
+                && node
+                    .children()
+                    .iter()
+                    .all(|&child| never_fails(egraph, child, seen))
+        }
+    });
+    seen.remove(&id);
+    total
+}
+
+/// The smallest program equal to `program` under [`rules`] and constant
+/// folding. Programs the e-graph cannot express come back unchanged.
+pub fn simplify(program: &Expr) -> Expr {
+    let mut expr = RecExpr::default();
+    if add_expr(program, &mut expr).is_none() {
+        return program.clone();
+    }
+    let runner = Runner::<SLIALang, ConstantFold>::default()
+        .with_iter_limit(10)
+        .with_node_limit(10_000)
+        .with_expr(&expr)
+        .run(&rules());
+    let (_, best) = Extractor::new(&runner.egraph, AstSize).find_best(runner.roots[0]);
+    crate::to_expr(&best).unwrap_or_else(|_| program.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::Func;
+
+    #[test]
+    fn simplify_programs() {
+        let name = || Expr::Var("name".into());
+        let s = |s: &str| Expr::ConstStr(s.into());
+        let n = Expr::ConstInt;
+
+        let whole = Expr::call(Func::SubStr(name(), n(0), Expr::call(Func::StrLen(name()))));
+        assert_eq!(simplify(&Expr::call(Func::Append(whole, s("")))), name());
+
+        // constants fold through the interpreter
+        let three = Expr::call(Func::StrLen(s("abc")));
+        let cut = Expr::call(Func::Min(Expr::call(Func::StrLen(name())), three));
+        let program = Expr::call(Func::SubStr(
+            name(),
+            Expr::call(Func::Mult(n(2), n(0))),
+            cut,
+        ));
+        let simplified = Expr::call(Func::SubStr(
+            name(),
+            n(0),
+            Expr::call(Func::Min(Expr::call(Func::StrLen(name())), n(3))),
+        ));
+        assert_eq!(simplify(&program), simplified);
+
+        // operands that may fail are not dropped
+        let len = || Expr::call(Func::StrLen(name()));
+        assert_eq!(simplify(&Expr::call(Func::Min(len(), len()))), n(0));
+        let ratio = Expr::call(Func::Div(len(), Expr::call(Func::StrLen(s("")))));
+        let failing = Expr::call(Func::Mult(ratio.clone(), n(0)));
+        let env = HashMap::from([("name".to_string(), s("ab"))]);
+        assert!(simplify(&failing).eval(&env).is_err());
+        let failing = Expr::call(Func::Min(ratio.clone(), ratio));
+        assert!(simplify(&failing).eval(&env).is_err());
+    }
+}
@@ -0,0 +1,387 @@
+use crate::language::{Expr, Func};
+use egg::{define_language, Id, Symbol};
+use std::{collections::HashMap, fmt, str::FromStr};
+
+define_language! {
+    /// The e-graph language: one node per `Func` that takes only `Expr`
+    /// arguments, typed holes, inverse-semantics nodes, constants and
+    /// variables.
+    pub enum SLIALang {
+        "Append" = Append([Id; 2]),
+        "StrLen" = StrLen(Id),
+        "StrAt" = StrAt([Id; 2]),
+        "SubStr" = SubStr([Id; 3]),
+        "IsPre" = IsPre([Id; 2]),
+        "IsPost" = IsPost([Id; 2]),
+        "Contains" = Contains([Id; 2]),
+        "Index" = Index([Id; 3]),
+        "Replace" = Replace([Id; 3]),
+        "ReplaceAll" = ReplaceAll([Id; 3]),
+        "IsDigit" = IsDigit(Id),
+        "ToCode" = ToCode(Id),
+        "FromCode" = FromCode(Id),
+
+        "Leq" = Leq([Id; 2]),
+        "Geq" = Geq([Id; 2]),
+        "Lt" = Lt([Id; 2]),
+        "Gt" = Gt([Id; 2]),
+        "Eql" = Eql([Id; 2]),
+        "Add" = Add([Id; 2]),
+        "Min" = Min([Id; 2]),
+        "Mult" = Mult([Id; 2]),
+        "Div" = Div([Id; 2]),
+        "Abs" = Abs(Id),
+        "Mod" = Mod([Id; 2]),
+        "NegI" = NegI(Id),
+
+        "NegB" = NegB(Id),
+        "And" = And([Id; 2]),
+        "Or" = Or([Id; 2]),
+        "Xor" = Xor([Id; 2]),
+        "Implies" = Implies([Id; 2]),
+        "LexEq" = LexEq([Id; 2]),
+        "LexLt" = LexLt([Id; 2]),
+        "LexLeq" = LexLeq([Id; 2]),
+        "LexGeq" = LexGeq([Id; 2]),
+
+        "StrToInt" = StrToInt(Id),
+        "IntToStr" = IntToStr(Id),
+
+        // the specification of the whole program
+        "root_spec" = RootSpec,
+        Int(i64),
+        Bool(bool),
+        Str(Str),
+        // a hole of the given sort, whose child carries its specification
+        Hole(Sort, Id),
+        // the specification of one argument, inverted from its parent's
+        Inv(Tag, Id),
+        // a program parameter, when simplifying a complete program; last, as
+        // any name would parse as one
+        Var(Symbol),
+    }
+}
+
+/// The sorts a hole can be filled with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Sort {
+    Int,
+    String,
+    Bool,
+}
+
+impl fmt::Display for Sort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl FromStr for Sort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Int" => Ok(Sort::Int),
+            "String" => Ok(Sort::String),
+            "Bool" => Ok(Sort::Bool),
+            _ => Err(format!("not a sort: {}", s)),
+        }
+    }
+}
+
+/// A string constant, written with surrounding quotes so that it cannot be
+/// confused with an operator. Those quotes are escaped, as are quotes,
+/// backslashes and line breaks inside, because the s-expression reader that
+/// parses a `RecExpr` drops a bare leading quote, ends a quoted token at any
+/// quote not after a backslash, and splits bare tokens at line breaks.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Str(pub String);
+
+impl fmt::Display for Str {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("\\\"")?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                c => write!(f, "{}", c)?,
+            }
+        }
+        f.write_str("\\\"")
+    }
+}
+
+impl FromStr for Str {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("not a string constant: {}", s);
+        let body = s
+            .strip_prefix("\\\"")
+            .and_then(|s| s.strip_suffix("\\\""))
+            .ok_or_else(error)?;
+        let mut string = String::new();
+        let mut chars = body.chars();
+        while let Some(c) = chars.next() {
+            string.push(match c {
+                '\\' => match chars.next() {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    _ => return Err(error()),
+                },
+                '"' => return Err(error()),
+                c => c,
+            });
+        }
+        Ok(Str(string))
+    }
+}
+
+/// Declares [`Op`] with one variant per operator of both [`SLIALang`] and
+/// [`Func`], and the conversions from either into it.
+macro_rules! operators {
+    ($($op:ident),* $(,)?) => {
+        /// The operators of [`SLIALang`], without their children
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub enum Op {
+            $($op),*
+        }
+
+        impl Op {
+            const ALL: &'static [Op] = &[$(Op::$op),*];
+
+            /// The operator of an e-node, if it is an application
+            pub fn of_node(node: &SLIALang) -> Option<Op> {
+                match node {
+                    $(SLIALang::$op(..) => Some(Op::$op),)*
+                    _ => None,
+                }
+            }
+
+            /// The operator of a call, unless it takes a regular expression
+            pub fn of_func(func: &Func) -> Option<Op> {
+                match func {
+                    $(Func::$op(..) => Some(Op::$op),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+operators! {
+    Append,
+    StrLen,
+    StrAt,
+    SubStr,
+    IsPre,
+    IsPost,
+    Contains,
+    Index,
+    Replace,
+    ReplaceAll,
+    IsDigit,
+    ToCode,
+    FromCode,
+    Leq,
+    Geq,
+    Lt,
+    Gt,
+    Eql,
+    Add,
+    Min,
+    Mult,
+    Div,
+    Abs,
+    Mod,
+    NegI,
+    NegB,
+    And,
+    Or,
+    Xor,
+    Implies,
+    LexEq,
+    LexLt,
+    LexLeq,
+    LexGeq,
+    StrToInt,
+    IntToStr,
+}
+
+impl Op {
+    pub fn arity(self) -> usize {
+        match self {
+            Op::StrLen
+            | Op::IsDigit
+            | Op::ToCode
+            | Op::FromCode
+            | Op::Abs
+            | Op::NegI
+            | Op::NegB
+            | Op::StrToInt
+            | Op::IntToStr => 1,
+            Op::SubStr | Op::Index | Op::Replace | Op::ReplaceAll => 3,
+            _ => 2,
+        }
+    }
+
+    /// Whether the operator fails on some arguments of its sorts: integer
+    /// overflow, or division by zero
+    pub fn partial(self) -> bool {
+        matches!(
+            self,
+            Op::Add | Op::Min | Op::Mult | Op::Div | Op::Abs | Op::Mod | Op::NegI | Op::StrToInt
+        )
+    }
+
+    /// The call of this operator on `args`, or `None` if they are not as
+    /// many as its arity
+    pub fn call(self, args: &[Expr]) -> Option<Func> {
+        let call = match (self, args) {
+            (Op::Append, [a, b]) => Func::Append(a.clone(), b.clone()),
+            (Op::StrLen, [a]) => Func::StrLen(a.clone()),
+            (Op::StrAt, [a, b]) => Func::StrAt(a.clone(), b.clone()),
+            (Op::SubStr, [a, b, c]) => Func::SubStr(a.clone(), b.clone(), c.clone()),
+            (Op::IsPre, [a, b]) => Func::IsPre(a.clone(), b.clone()),
+            (Op::IsPost, [a, b]) => Func::IsPost(a.clone(), b.clone()),
+            (Op::Contains, [a, b]) => Func::Contains(a.clone(), b.clone()),
+            (Op::Index, [a, b, c]) => Func::Index(a.clone(), b.clone(), c.clone()),
+            (Op::Replace, [a, b, c]) => Func::Replace(a.clone(), b.clone(), c.clone()),
+            (Op::ReplaceAll, [a, b, c]) => Func::ReplaceAll(a.clone(), b.clone(), c.clone()),
+            (Op::IsDigit, [a]) => Func::IsDigit(a.clone()),
+            (Op::ToCode, [a]) => Func::ToCode(a.clone()),
+            (Op::FromCode, [a]) => Func::FromCode(a.clone()),
+            (Op::Leq, [a, b]) => Func::Leq(a.clone(), b.clone()),
+            (Op::Geq, [a, b]) => Func::Geq(a.clone(), b.clone()),
+            (Op::Lt, [a, b]) => Func::Lt(a.clone(), b.clone()),
+            (Op::Gt, [a, b]) => Func::Gt(a.clone(), b.clone()),
+            (Op::Eql, [a, b]) => Func::Eql(a.clone(), b.clone()),
+            (Op::Add, [a, b]) => Func::Add(a.clone(), b.clone()),
+            (Op::Min, [a, b]) => Func::Min(a.clone(), b.clone()),
+            (Op::Mult, [a, b]) => Func::Mult(a.clone(), b.clone()),
+            (Op::Div, [a, b]) => Func::Div(a.clone(), b.clone()),
+            (Op::Abs, [a]) => Func::Abs(a.clone()),
+            (Op::Mod, [a, b]) => Func::Mod(a.clone(), b.clone()),
+            (Op::NegI, [a]) => Func::NegI(a.clone()),
+            (Op::NegB, [a]) => Func::NegB(a.clone()),
+            (Op::And, [a, b]) => Func::And(a.clone(), b.clone()),
+            (Op::Or, [a, b]) => Func::Or(a.clone(), b.clone()),
+            (Op::Xor, [a, b]) => Func::Xor(a.clone(), b.clone()),
+            (Op::Implies, [a, b]) => Func::Implies(a.clone(), b.clone()),
+            (Op::LexEq, [a, b]) => Func::LexEq(a.clone(), b.clone()),
+            (Op::LexLt, [a, b]) => Func::LexLt(a.clone(), b.clone()),
+            (Op::LexLeq, [a, b]) => Func::LexLeq(a.clone(), b.clone()),
+            (Op::LexGeq, [a, b]) => Func::LexGeq(a.clone(), b.clone()),
+            (Op::StrToInt, [a]) => Func::StrToInt(a.clone()),
+            (Op::IntToStr, [a]) => Func::IntToStr(a.clone()),
+            _ => return None,
+        };
+        Some(call)
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Extraction costs of operators, taken from the weights of the grammar
+/// productions that use them. Anything unweighted costs 1, so that without
+/// weights the cost of a program is its size.
+#[derive(Clone, Debug, Default)]
+pub struct Weights(pub HashMap<Op, usize>);
+
+impl Weights {
+    pub fn op(&self, op: Op) -> usize {
+        self.0.get(&op).copied().unwrap_or(1)
+    }
+
+    pub fn node(&self, node: &SLIALang) -> usize {
+        Op::of_node(node).map_or(1, |op| self.op(op))
+    }
+
+    /// The summed weight of every node of `e`
+    pub fn expr(&self, e: &Expr) -> usize {
+        match e {
+            Expr::Call(f) => {
+                let args: usize = f.args().into_iter().map(|a| self.expr(a)).sum();
+                Op::of_func(f).map_or(1, |op| self.op(op)) + args
+            }
+            Expr::If(c, t, e) => 1 + self.expr(c) + self.expr(t) + self.expr(e),
+            _ => 1,
+        }
+    }
+}
+
+/// Names argument `arg` of `op`. Written `inv.SubStr.0` in patterns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Tag {
+    pub op: Op,
+    pub arg: usize,
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "inv.{}.{}", self.op, self.arg)
+    }
+}
+
+impl FromStr for Tag {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tag = s
+            .strip_prefix("inv.")
+            .and_then(|s| s.split_once('.'))
+            .and_then(|(op, arg)| {
+                let op = *Op::ALL.iter().find(|o| o.to_string() == op)?;
+                let arg = arg.parse().ok().filter(|arg| *arg < op.arity())?;
+                Some(Tag { op, arg })
+            });
+        tag.ok_or_else(|| format!("not an inverse tag: {}", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egg::RecExpr;
+
+    #[test]
+    fn parse_typed_nodes() {
+        let text = "(SubStr (String (inv.SubStr.0 root_spec)) 0 (Int (inv.SubStr.2 root_spec)))";
+        let expr: RecExpr<SLIALang> = text.parse().unwrap();
+        assert_eq!(expr.to_string(), text);
+        assert!(matches!(
+            expr[1.into()],
+            SLIALang::Inv(
+                Tag {
+                    op: Op::SubStr,
+                    arg: 0
+                },
+                _
+            )
+        ));
+        assert!(matches!(expr[2.into()], SLIALang::Hole(Sort::String, _)));
+
+        // tags are checked against the operator's arity
+        assert!("inv.StrLen.1".parse::<Tag>().is_err());
+        assert!("(Int (inv.Substr.0 root_spec))"
+            .parse::<RecExpr<SLIALang>>()
+            .is_err());
+
+        // string constants round-trip whatever they hold
+        for s in ["", "a b", "say \"hi\"", "(x)\\", "two\nlines"] {
+            let mut expr = RecExpr::default();
+            let id = expr.add(SLIALang::Str(Str(s.to_string())));
+            expr.add(SLIALang::StrLen(id));
+            let parsed: RecExpr<SLIALang> = expr.to_string().parse().unwrap();
+            assert_eq!(parsed, expr);
+        }
+    }
+}
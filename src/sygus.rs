@@ -2,9 +2,9 @@ use core::fmt;
 use pest::{error::Error, iterators::Pair, Parser};
 use pest_derive::Parser;
 use std::{collections::HashMap, fmt::Display};
-use substring::Substring;
 
-use crate::language::{Expr, Func};
+use crate::language::{Eval, Expr, Func, RegFun, RegLang};
+use crate::slia::{Op, Weights};
 
 #[derive(Parser)]
 #[grammar = "sygus.pest"]
@@ -30,6 +30,8 @@ pub struct Conjecture {
     pub signature: HashMap<String, i32>,
     /// The SyGuS logic
     pub logic: Option<String>,
+    /// Declared weights with their default values
+    pub weights: Vec<(String, usize)>,
 }
 
 impl Conjecture {
@@ -41,6 +43,7 @@ impl Conjecture {
             assumptions: Vec::new(),
             signature: HashMap::new(),
             logic: None,
+            weights: Vec::new(),
         }
     }
 }
@@ -73,6 +76,35 @@ impl Conjecture {
         }
         Some(examples)
     }
+
+    /// The strings that the synthesized regular expression `name` must accept
+    /// and reject, from constraints `(str.in_re "..." (name))` and their negations.
+    pub fn regex_specification(&self, name: &str) -> Option<(Vec<String>, Vec<String>)> {
+        let (mut positives, mut negatives) = (Vec::new(), Vec::new());
+        for constraint in &self.constraints {
+            let (examples, membership) = match constraint {
+                Term::Application(f, terms) if f == "not" => (&mut negatives, terms.first()?),
+                membership => (&mut positives, membership),
+            };
+            match membership {
+                Term::Application(f, terms) if f == "str.in_re" || f == "str.in.re" => {
+                    match terms.as_slice() {
+                        [Term::Literal(Literal::String(s)), Term::Application(g, args)]
+                            if g == name && args.is_empty() =>
+                        {
+                            examples.push(s.to_owned())
+                        }
+                        [Term::Literal(Literal::String(s)), Term::Identifier(g)] if g == name => {
+                            examples.push(s.to_owned())
+                        }
+                        _ => return None,
+                    }
+                }
+                _ => return None,
+            }
+        }
+        Some((positives, negatives))
+    }
 }
 
 impl Default for Conjecture {
@@ -89,16 +121,107 @@ pub struct Function {
     pub grammar: Grammar,
 }
 
+/// A function given by its body, as in a `define-fun` command
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub name: String,
+    pub parameters: Vec<(String, Sort)>,
+    pub return_sort: Sort,
+    pub body: Term,
+}
+
 #[derive(Debug)]
 pub struct Grammar {
     pub declarations: Vec<(String, Sort)>,
     pub rules: Vec<(String, Sort, Vec<GTerm>)>,
+    /// The weight of each production of each nonterminal, in rule order.
+    /// Productions without one weigh 1.
+    pub weights: HashMap<String, Vec<usize>>,
+}
+
+impl Grammar {
+    pub fn weight(&self, nonterminal: &str, production: usize) -> usize {
+        self.weights
+            .get(nonterminal)
+            .and_then(|ws| ws.get(production))
+            .copied()
+            .unwrap_or(1)
+    }
+
+    /// Extraction costs of the operators in the grammar. An operator used
+    /// by several productions costs as much as the cheapest.
+    pub fn op_weights(&self) -> Weights {
+        let mut weights: HashMap<Op, usize> = HashMap::new();
+        for (name, _, rhs) in &self.rules {
+            for (i, g_term) in rhs.iter().enumerate() {
+                if let GTerm::BFTerm(BFTerm::Application(f, args)) = g_term {
+                    if let Some(op) = operator(f, args.len()) {
+                        let w = weights.entry(op).or_insert(usize::MAX);
+                        *w = (*w).min(self.weight(name, i));
+                    }
+                }
+            }
+        }
+        Weights(weights)
+    }
+
+    /// Replaces the weights with ones learned from `corpus`, a set of
+    /// previous solutions.
+    ///
+    /// Each production is weighted by its surprisal, `-log2 p`, where `p` is
+    /// how often its operator, variable or literal heads a subterm of the
+    /// corpus among the alternatives of its nonterminal, with add-one
+    /// smoothing. Weights are rounded up, and are never below 1.
+    pub fn learn_weights(&mut self, corpus: &[Term]) {
+        fn count(term: &Term, counts: &mut HashMap<String, usize>) {
+            *counts.entry(head(term)).or_default() += 1;
+            if let Term::Application(_, args) = term {
+                args.iter().for_each(|arg| count(arg, counts));
+            }
+        }
+        fn head(term: &Term) -> String {
+            match term {
+                Term::Application(f, args) => format!("{}/{}", f, args.len()),
+                term => term.to_string(),
+            }
+        }
+        fn production(g_term: &GTerm) -> Option<String> {
+            match g_term {
+                GTerm::BFTerm(BFTerm::Application(f, args)) => {
+                    Some(format!("{}/{}", f, args.len()))
+                }
+                GTerm::BFTerm(BFTerm::Identifier(name)) => Some(name.to_owned()),
+                GTerm::BFTerm(BFTerm::Literal(lit)) => Some(lit.to_string()),
+                _ => None,
+            }
+        }
+
+        let mut counts = HashMap::new();
+        corpus.iter().for_each(|term| count(term, &mut counts));
+        for (name, _, rhs) in &self.rules {
+            let uses: Vec<usize> = rhs
+                .iter()
+                .map(|g| {
+                    production(g)
+                        .and_then(|p| counts.get(&p))
+                        .copied()
+                        .unwrap_or(0)
+                })
+                .collect();
+            let total = (uses.iter().sum::<usize>() + uses.len()) as f64;
+            let weights = uses
+                .iter()
+                .map(|n| (-((n + 1) as f64 / total).log2()).ceil().max(1.0) as usize)
+                .collect();
+            self.weights.insert(name.to_owned(), weights);
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum GTerm {
-    // Constant(Sort),
-    // Variable(Sort),
+    Constant(Sort),
+    Variable(Sort),
     BFTerm(BFTerm),
 }
 
@@ -125,7 +248,7 @@ pub enum BFTerm {
     // Annotated(Box<BFTerm>, Vec<Attribute>), // Unimplemented
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Literal {
     Numeral(i64),
     // Decimal(f64), // Unsupported
@@ -157,7 +280,7 @@ impl fmt::Display for Literal {
             Literal::Numeral(n) => write!(f, "{}", n),
             Literal::Bool(true) => write!(f, "true"),
             Literal::Bool(false) => write!(f, "false"),
-            Literal::String(s) => write!(f, "\"{}\"", s.replace('"', "\\\"")),
+            Literal::String(s) => write!(f, "\"{}\"", s.replace('"', "\"\"")),
         }
     }
 }
@@ -189,6 +312,20 @@ impl fmt::Display for Term {
     }
 }
 
+/// The call of a left-associative operator of SMT-LIB on `params`, so that
+/// `(+ a b c)` is `(+ (+ a b) c)`
+fn fold_left(params: &[Term], f: fn(Expr, Expr) -> Func) -> Result<Box<Func>, ()> {
+    let mut args = params.iter().map(Expr::try_from);
+    let (Some(a), Some(b)) = (args.next(), args.next()) else {
+        return Err(());
+    };
+    let mut call = f(a?, b?);
+    for arg in args {
+        call = f(Expr::Call(Box::new(call)), arg?);
+    }
+    Ok(Box::new(call))
+}
+
 impl TryFrom<&Term> for Expr {
     type Error = ();
 
@@ -198,158 +335,278 @@ impl TryFrom<&Term> for Expr {
             Term::Literal(Literal::Bool(b)) => Ok(Expr::ConstBool(b.to_owned())),
             Term::Literal(Literal::Numeral(n)) => Ok(Expr::ConstInt(n.to_owned())),
             Term::Literal(Literal::String(s)) => Ok(Expr::ConstStr(s.to_owned())),
+            Term::Application(f, params) if f == "ite" && params.len() >= 3 => Ok(Expr::If(
+                Box::new((&params[0]).try_into()?),
+                Box::new((&params[1]).try_into()?),
+                Box::new((&params[2]).try_into()?),
+            )),
             Term::Application(f, params) => Ok(Expr::Call(match f.as_str() {
-                "str.++" if params.len() >= 2 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
-                    Box::new(Func::Append(a, b))
-                }
+                "str.++" if params.len() >= 2 => fold_left(params, Func::Append)?,
                 "str.len" if !params.is_empty() => {
-                    let a = params.get(0).unwrap().try_into()?;
+                    let a = (&params[0]).try_into()?;
                     Box::new(Func::StrLen(a))
                 }
                 "str.at" if params.len() >= 2 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
                     Box::new(Func::StrAt(a, b))
                 }
                 "str.substr" if params.len() >= 3 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
-                    let c = params.get(2).unwrap().try_into()?;
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
+                    let c = (&params[2]).try_into()?;
                     Box::new(Func::SubStr(a, b, c))
                 }
                 "str.prefixof" if params.len() >= 2 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
                     Box::new(Func::IsPre(a, b))
                 }
                 "str.suffixof" if params.len() >= 2 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
                     Box::new(Func::IsPost(a, b))
                 }
                 "str.contains" if params.len() >= 2 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
                     Box::new(Func::Contains(a, b))
                 }
                 "str.indexof" if params.len() >= 3 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
-                    let c = params.get(2).unwrap().try_into()?;
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
+                    let c = (&params[2]).try_into()?;
                     Box::new(Func::Index(a, b, c))
                 }
                 "str.replace" if params.len() >= 3 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
-                    let c = params.get(2).unwrap().try_into()?;
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
+                    let c = (&params[2]).try_into()?;
                     Box::new(Func::Replace(a, b, c))
                 }
-                "str.replace" if params.len() >= 3 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
-                    let c = params.get(2).unwrap().try_into()?;
-                    Box::new(Func::Replace(a, b, c))
-                }
-                "str.replace_all" if params.len() >= 3 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
-                    let c = params.get(2).unwrap().try_into()?;
+                "str.replace_all" | "str.replaceall" if params.len() >= 3 => {
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
+                    let c = (&params[2]).try_into()?;
                     Box::new(Func::ReplaceAll(a, b, c))
                 }
-                "<=" if params.len() >= 2 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
-                    Box::new(Func::Leq(a, b))
+                "str.replace_re" if params.len() >= 3 => {
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
+                    let c = (&params[2]).try_into()?;
+                    Box::new(Func::ReplaceRe(a, b, c))
+                }
+                "str.replace_re_all" if params.len() >= 3 => {
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
+                    let c = (&params[2]).try_into()?;
+                    Box::new(Func::ReplaceReAll(a, b, c))
+                }
+                "str.in_re" | "str.in.re" if params.len() >= 2 => {
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
+                    Box::new(Func::InRe(a, b))
+                }
+                "str.is_digit" if !params.is_empty() => {
+                    let a = (&params[0]).try_into()?;
+                    Box::new(Func::IsDigit(a))
+                }
+                "str.to_code" if !params.is_empty() => {
+                    let a = (&params[0]).try_into()?;
+                    Box::new(Func::ToCode(a))
+                }
+                "str.from_code" if !params.is_empty() => {
+                    let a = (&params[0]).try_into()?;
+                    Box::new(Func::FromCode(a))
                 }
                 "<=" if params.len() >= 2 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
                     Box::new(Func::Leq(a, b))
                 }
+                "<" if params.len() >= 2 => {
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
+                    Box::new(Func::Lt(a, b))
+                }
+                ">" if params.len() >= 2 => {
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
+                    Box::new(Func::Gt(a, b))
+                }
                 ">=" if params.len() >= 2 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
                     Box::new(Func::Geq(a, b))
                 }
                 "=" if params.len() >= 2 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
                     Box::new(Func::Eql(a, b))
                 }
-                "+" if params.len() >= 2 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
-                    Box::new(Func::Add(a, b))
-                }
-                "-" if params.len() >= 2 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
-                    Box::new(Func::Min(a, b))
-                }
+                "+" if params.len() >= 2 => fold_left(params, Func::Add)?,
+                "-" if params.len() >= 2 => fold_left(params, Func::Min)?,
                 "-" if !params.is_empty() => {
-                    let a = params.get(0).unwrap().try_into()?;
+                    let a = (&params[0]).try_into()?;
                     Box::new(Func::NegI(a))
                 }
-                "*" if params.len() >= 2 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
-                    Box::new(Func::Mult(a, b))
-                }
+                "*" if params.len() >= 2 => fold_left(params, Func::Mult)?,
                 "div" if params.len() >= 2 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
                     Box::new(Func::Div(a, b))
                 }
                 "abs" if !params.is_empty() => {
-                    let a = params.get(0).unwrap().try_into()?;
+                    let a = (&params[0]).try_into()?;
                     Box::new(Func::Abs(a))
                 }
                 "mod" if params.len() >= 2 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
                     Box::new(Func::Mod(a, b))
                 }
-                "and" if params.len() >= 2 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
-                    Box::new(Func::And(a, b))
-                }
-                "or" if params.len() >= 2 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
-                    Box::new(Func::Or(a, b))
+                "and" if params.len() >= 2 => fold_left(params, Func::And)?,
+                "or" if params.len() >= 2 => fold_left(params, Func::Or)?,
+                "str.<" if params.len() >= 2 => {
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
+                    Box::new(Func::LexLt(a, b))
                 }
                 "str.<=" if params.len() >= 2 => {
-                    let a = params.get(0).unwrap().try_into()?;
-                    let b = params.get(1).unwrap().try_into()?;
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
                     Box::new(Func::LexLeq(a, b))
                 }
-                "int.to.str" if !params.is_empty() => {
-                    let a = params.get(0).unwrap().try_into()?;
+                "str.from_int" | "int.to.str" if !params.is_empty() => {
+                    let a = (&params[0]).try_into()?;
                     Box::new(Func::IntToStr(a))
                 }
-                "str.to.int" if !params.is_empty() => {
-                    let a = params.get(0).unwrap().try_into()?;
+                "str.to_int" | "str.to.int" if !params.is_empty() => {
+                    let a = (&params[0]).try_into()?;
                     Box::new(Func::StrToInt(a))
                 }
+                // `not` and `distinct` are lowered to equivalent functions
+                "not" if !params.is_empty() => {
+                    let a = (&params[0]).try_into()?;
+                    Box::new(Func::NegB(a))
+                }
+                "=>" if params.len() >= 2 => {
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
+                    Box::new(Func::Implies(a, b))
+                }
+                "xor" if params.len() >= 2 => {
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
+                    Box::new(Func::Xor(a, b))
+                }
+                "distinct" if params.len() >= 2 => {
+                    let a = (&params[0]).try_into()?;
+                    let b = (&params[1]).try_into()?;
+                    Box::new(Func::NegB(Expr::call(Func::Eql(a, b))))
+                }
                 _ => Err(())?,
             })),
         }
     }
 }
 
+/// The operator a SyGuS function symbol of the given arity lowers to, for
+/// those that lower to a single [`Op`].
+pub(crate) fn operator(name: &str, arity: usize) -> Option<Op> {
+    Some(match (name, arity) {
+        ("str.++", _) => Op::Append,
+        ("str.len", _) => Op::StrLen,
+        ("str.at", _) => Op::StrAt,
+        ("str.substr", _) => Op::SubStr,
+        ("str.prefixof", _) => Op::IsPre,
+        ("str.suffixof", _) => Op::IsPost,
+        ("str.contains", _) => Op::Contains,
+        ("str.indexof", _) => Op::Index,
+        ("str.replace", _) => Op::Replace,
+        ("str.replace_all" | "str.replaceall", _) => Op::ReplaceAll,
+        ("str.is_digit", _) => Op::IsDigit,
+        ("str.to_code", _) => Op::ToCode,
+        ("str.from_code", _) => Op::FromCode,
+        ("<=", _) => Op::Leq,
+        (">=", _) => Op::Geq,
+        ("<", _) => Op::Lt,
+        (">", _) => Op::Gt,
+        ("=", _) => Op::Eql,
+        ("+", _) => Op::Add,
+        ("-", 1) => Op::NegI,
+        ("-", _) => Op::Min,
+        ("*", _) => Op::Mult,
+        ("div", _) => Op::Div,
+        ("abs", _) => Op::Abs,
+        ("mod", _) => Op::Mod,
+        ("not", _) => Op::NegB,
+        ("and", _) => Op::And,
+        ("or", _) => Op::Or,
+        ("xor", _) => Op::Xor,
+        ("=>", _) => Op::Implies,
+        ("str.<", _) => Op::LexLt,
+        ("str.<=", _) => Op::LexLeq,
+        ("str.from_int" | "int.to.str", _) => Op::IntToStr,
+        ("str.to_int" | "str.to.int", _) => Op::StrToInt,
+        _ => return None,
+    })
+}
+
 pub fn parse_file(file: &str) -> Result<Conjecture, Box<Error<Rule>>> {
     let sygus = SygusParser::parse(Rule::sygus, file)?.next().unwrap();
     Ok(parse_conjecture(sygus))
 }
 
+/// The bodies of the `define-fun` commands in `file`, such as the solutions
+/// printed by a previous run.
+pub fn parse_solutions(file: &str) -> Result<Vec<Term>, Box<Error<Rule>>> {
+    let definitions = parse_definitions(file)?;
+    Ok(definitions.into_iter().map(|d| d.body).collect())
+}
+
+/// The `define-fun` commands in `file`, in order
+pub fn parse_definitions(file: &str) -> Result<Vec<Definition>, Box<Error<Rule>>> {
+    let sygus = SygusParser::parse(Rule::sygus, file)?.next().unwrap();
+    Ok(sygus
+        .into_inner()
+        .filter(|pair| pair.as_rule() == Rule::define_fun)
+        .map(|pair| {
+            let mut inner_rules = pair.into_inner();
+            let name = inner_rules.next().unwrap().as_str().to_string();
+            let mut parameters = Vec::new();
+            let mut inner_rules = inner_rules.peekable();
+            while let Some(pair) = inner_rules.next_if(|p| p.as_rule() == Rule::sorted_var) {
+                let mut inner_rules = pair.into_inner();
+                let name = inner_rules.next().unwrap().as_str().to_string();
+                parameters.push((name, parse_sort(inner_rules.next().unwrap())));
+            }
+            let return_sort = parse_sort(inner_rules.next().unwrap());
+            let body = parse_term(inner_rules.next().unwrap());
+            Definition {
+                name,
+                parameters,
+                return_sort,
+                body,
+            }
+        })
+        .collect())
+}
+
 fn parse_conjecture(pair: Pair<Rule>) -> Conjecture {
     let mut conjecture = Conjecture::new();
 
     for pair in pair.into_inner() {
         match pair.as_rule() {
+            Rule::declare_weight => {
+                let mut inner_rules = pair.into_inner();
+                let name = inner_rules.next().unwrap().as_str().to_string();
+                let default = parse_attributes(inner_rules)
+                    .into_iter()
+                    .find(|(key, _)| key == ":default")
+                    .map_or(0, |(_, value)| value);
+                conjecture.weights.push((name, default));
+            }
             Rule::set_logic => {
                 conjecture.logic = Some(pair.into_inner().next().unwrap().as_str().to_string())
             }
@@ -368,7 +625,35 @@ fn parse_conjecture(pair: Pair<Rule>) -> Conjecture {
                     })
                     .collect();
                 let return_sort = parse_sort(inner_rules.next().unwrap());
-                let grammar = parse_grammar(inner_rules.next().unwrap());
+                let mut grammar = parse_grammar(inner_rules.next().unwrap(), &conjecture.weights);
+                // `(Variable T)` stands for every parameter of sort `T`
+                for (name, _, terms) in &mut grammar.rules {
+                    let weights = grammar.weights.get_mut(name);
+                    let old_weights = weights.as_ref().map(|ws| ws.to_vec());
+                    let mut new_weights = Vec::new();
+                    *terms = terms
+                        .drain(..)
+                        .enumerate()
+                        .flat_map(|(i, term)| {
+                            let expanded = match term {
+                                GTerm::Variable(sort) => parameters
+                                    .iter()
+                                    .filter(|(_, s)| *s == sort)
+                                    .map(|(name, _)| {
+                                        GTerm::BFTerm(BFTerm::Identifier(name.clone()))
+                                    })
+                                    .collect(),
+                                term => vec![term],
+                            };
+                            let weight = old_weights.as_ref().map_or(1, |ws| ws[i]);
+                            new_weights.extend(expanded.iter().map(|_| weight));
+                            expanded
+                        })
+                        .collect();
+                    if let Some(weights) = weights {
+                        *weights = new_weights;
+                    }
+                }
                 conjecture.functions_to_synthesize.push(Function {
                     name,
                     parameters,
@@ -403,9 +688,13 @@ fn parse_sort(pair: Pair<Rule>) -> Sort {
     }
 }
 
-fn parse_grammar(pair: Pair<Rule>) -> Grammar {
+/// Parses a grammar, weighting each production by the sum of its declared
+/// weights, or their defaults where it is not annotated. With no weights
+/// declared, a production weighs the sum of its annotations.
+fn parse_grammar(pair: Pair<Rule>, declared: &[(String, usize)]) -> Grammar {
     let mut declarations = Vec::new();
     let mut rules: Vec<(String, Sort, Vec<GTerm>)> = Vec::new();
+    let mut weights = HashMap::new();
 
     for pair in pair.into_inner() {
         match pair.as_rule() {
@@ -419,7 +708,15 @@ fn parse_grammar(pair: Pair<Rule>) -> Grammar {
                 let mut inner_rules = pair.into_inner();
                 let name = inner_rules.next().unwrap().as_str().to_string();
                 let sort = parse_sort(inner_rules.next().unwrap());
-                let terms: Vec<GTerm> = inner_rules.map(parse_g_term).collect();
+                let (terms, annotations): (Vec<GTerm>, Vec<Vec<(String, usize)>>) =
+                    inner_rules.map(parse_g_term).unzip();
+                if annotations.iter().any(|a| !a.is_empty()) || !declared.is_empty() {
+                    let production_weights = annotations
+                        .iter()
+                        .map(|annotation| production_weight(annotation, declared))
+                        .collect();
+                    weights.insert(name.clone(), production_weights);
+                }
                 rules.push((name, sort, terms));
             }
             _ => unimplemented!("Unsupported rule in grammar: {:#?}", pair),
@@ -429,28 +726,77 @@ fn parse_grammar(pair: Pair<Rule>) -> Grammar {
     Grammar {
         declarations,
         rules,
+        weights,
     }
 }
 
-fn parse_g_term(pair: Pair<Rule>) -> GTerm {
+fn production_weight(annotation: &[(String, usize)], declared: &[(String, usize)]) -> usize {
+    let value = |name: &str| annotation.iter().find(|(key, _)| key == name);
+    let weight: usize = if declared.is_empty() {
+        annotation.iter().map(|(_, value)| value).sum()
+    } else {
+        declared
+            .iter()
+            .map(|(name, default)| value(&format!(":{}", name)).map_or(*default, |(_, v)| *v))
+            .sum()
+    };
+    // enumeration by weight needs every production to cost something
+    weight.max(1)
+}
+
+/// A grammar term, with the attributes it is annotated with
+fn parse_g_term(pair: Pair<Rule>) -> (GTerm, Vec<(String, usize)>) {
     let pair = pair.into_inner().next().unwrap();
     match pair.as_rule() {
-        Rule::bf_term => GTerm::BFTerm(parse_bf_term(pair)),
+        Rule::bf_term => {
+            let mut annotation = Vec::new();
+            let mut pair = pair;
+            // `(! t :w 2)` weighs the production `t`
+            while let Some(attributes) = pair.clone().into_inner().next() {
+                if attributes.as_rule() != Rule::bf_attributes {
+                    break;
+                }
+                let mut inner_rules = attributes.into_inner();
+                pair = inner_rules.next().unwrap();
+                annotation.extend(parse_attributes(inner_rules));
+            }
+            (GTerm::BFTerm(parse_bf_term(pair)), annotation)
+        }
+        Rule::constant => {
+            let sort = parse_sort(pair.into_inner().next().unwrap());
+            (GTerm::Constant(sort), Vec::new())
+        }
+        Rule::variable => {
+            let sort = parse_sort(pair.into_inner().next().unwrap());
+            (GTerm::Variable(sort), Vec::new())
+        }
         _ => unimplemented!("Unsupported g_term: {:#?}", pair),
     }
 }
 
+/// The attributes with numeral values, which are the only ones we use
+fn parse_attributes<'a>(pairs: impl Iterator<Item = Pair<'a, Rule>>) -> Vec<(String, usize)> {
+    pairs
+        .filter_map(|pair| {
+            let mut inner_rules = pair.into_inner();
+            let keyword = inner_rules.next()?.as_str().to_string();
+            let value = inner_rules.next()?.as_str().parse().ok()?;
+            Some((keyword, value))
+        })
+        .collect()
+}
+
 fn parse_bf_term(pair: Pair<Rule>) -> BFTerm {
     let pair = pair.into_inner().next().unwrap();
     match pair.as_rule() {
         Rule::bf_application => {
             let mut inner_rules = pair.into_inner();
-            let identifier = inner_rules.next().unwrap().as_str().to_string();
+            let identifier = parse_identifier(inner_rules.next().unwrap());
             let terms: Vec<BFTerm> = inner_rules.map(parse_bf_term).collect();
             BFTerm::Application(identifier, terms)
         }
         Rule::literal => BFTerm::Literal(parse_literal(pair)),
-        Rule::identifier => BFTerm::Identifier(pair.as_str().to_string()),
+        Rule::identifier => BFTerm::Identifier(parse_identifier(pair)),
         _ => unimplemented!("Unsupported bf_term: {:#?}", pair),
     }
 }
@@ -460,16 +806,45 @@ fn parse_term(pair: Pair<Rule>) -> Term {
     match pair.as_rule() {
         Rule::term_application => {
             let mut inner_rules = pair.into_inner();
-            let identifier = inner_rules.next().unwrap().as_str().to_string();
+            let identifier = parse_identifier(inner_rules.next().unwrap());
             let terms: Vec<Term> = inner_rules.map(parse_term).collect();
             Term::Application(identifier, terms)
         }
         Rule::literal => Term::Literal(parse_literal(pair)),
-        Rule::identifier => Term::Identifier(pair.as_str().to_string()),
+        Rule::identifier => Term::Identifier(parse_identifier(pair)),
         _ => unimplemented!("Unsupported term: {:#?}", pair),
     }
 }
 
+/// An identifier, written as `symbol` or `(_ symbol index ...)` whatever its
+/// spacing in the source
+fn parse_identifier(pair: Pair<Rule>) -> String {
+    let mut inner_rules = pair.into_inner();
+    let symbol = inner_rules.next().unwrap().as_str();
+    let indices: Vec<&str> = inner_rules.map(|index| index.as_str()).collect();
+    indexed(symbol, &indices)
+}
+
+/// The identifier `symbol` with `indices`, if there are any
+fn indexed(symbol: &str, indices: &[&str]) -> String {
+    if indices.is_empty() {
+        symbol.to_string()
+    } else {
+        format!("(_ {} {})", symbol, indices.join(" "))
+    }
+}
+
+/// The symbol and the indices of an identifier such as `(_ re.loop 1 3)`
+fn split_identifier(name: &str) -> Option<(&str, Vec<&str>)> {
+    let pair = SygusParser::parse(Rule::identifier, name).ok()?.next()?;
+    if pair.as_str() != name {
+        return None;
+    }
+    let mut inner_rules = pair.into_inner();
+    let symbol = inner_rules.next()?.as_str();
+    Some((symbol, inner_rules.map(|index| index.as_str()).collect()))
+}
+
 fn parse_literal(pair: Pair<Rule>) -> Literal {
     let pair = pair.into_inner().next().unwrap();
     match pair.as_rule() {
@@ -477,145 +852,368 @@ fn parse_literal(pair: Pair<Rule>) -> Literal {
         Rule::bool_const => Literal::Bool(pair.as_str().to_string().parse::<bool>().unwrap()),
         Rule::string_const => {
             let literal = pair.as_str().to_string();
-            Literal::String(
-                literal
-                    .substring(1, literal.len() - 1)
-                    .replace("\"\"", "\"")
-                    .to_string(),
-            )
+            Literal::String(literal[1..literal.len() - 1].replace("\"\"", "\""))
         }
         _ => unimplemented!("Unsupported literal: {:#?}", pair),
     }
 }
 
-impl Term {
-    pub fn eval(&self, env: &HashMap<String, Literal>) -> Option<Literal> {
-        match self {
-            Term::Identifier(s) => env.get(s).cloned(),
-            Term::Literal(lit) => Some(lit.clone()),
-            Term::Application(f, args) => {
-                let args = args.iter().map(|t| t.eval(env)).collect::<Option<_>>()?;
-                Self::call(f, args)
+impl TryFrom<&Term> for RegLang {
+    type Error = ();
+
+    fn try_from(value: &Term) -> Result<Self, Self::Error> {
+        match value {
+            Term::Identifier(name) => match name.as_str() {
+                "re.none" | "re.nostr" => Ok(RegLang::Nil),
+                "re.all" => Ok(RegLang::All),
+                "re.allchar" => Ok(RegLang::AllChar),
+                _ => Ok(RegLang::Var(name.to_owned())),
+            },
+            Term::Application(f, params) => {
+                let (f, indices) = split_identifier(f).ok_or(())?;
+                Ok(RegLang::RCall(match (f, indices.as_slice()) {
+                    ("str.to_re" | "str.to.re", []) if !params.is_empty() => {
+                        let a = (&params[0]).try_into()?;
+                        Box::new(RegFun::FromStr(a))
+                    }
+                    ("re.++", []) if params.len() >= 2 => {
+                        let a = (&params[0]).try_into()?;
+                        let b = (&params[1]).try_into()?;
+                        Box::new(RegFun::Sequence(a, b))
+                    }
+                    ("re.union", []) if params.len() >= 2 => {
+                        let a = (&params[0]).try_into()?;
+                        let b = (&params[1]).try_into()?;
+                        Box::new(RegFun::Union(a, b))
+                    }
+                    ("re.inter", []) if params.len() >= 2 => {
+                        let a = (&params[0]).try_into()?;
+                        let b = (&params[1]).try_into()?;
+                        Box::new(RegFun::Inter(a, b))
+                    }
+                    ("re.*", []) if !params.is_empty() => {
+                        let a = (&params[0]).try_into()?;
+                        Box::new(RegFun::Star(a))
+                    }
+                    ("re.+", []) if !params.is_empty() => {
+                        let a = (&params[0]).try_into()?;
+                        Box::new(RegFun::One(a))
+                    }
+                    ("re.opt", []) if !params.is_empty() => {
+                        let a = (&params[0]).try_into()?;
+                        Box::new(RegFun::Opt(a))
+                    }
+                    ("re.range", []) if params.len() >= 2 => {
+                        let a = (&params[0]).try_into()?;
+                        let b = (&params[1]).try_into()?;
+                        Box::new(RegFun::Range(a, b))
+                    }
+                    ("re.comp", []) if !params.is_empty() => {
+                        let a = (&params[0]).try_into()?;
+                        Box::new(RegFun::Comp(a))
+                    }
+                    ("re.diff", []) if params.len() >= 2 => {
+                        let a = (&params[0]).try_into()?;
+                        let b = (&params[1]).try_into()?;
+                        Box::new(RegFun::Diff(a, b))
+                    }
+                    ("re.^", [n]) if !params.is_empty() => {
+                        let a = (&params[0]).try_into()?;
+                        Box::new(RegFun::Power(a, n.parse().map_err(|_| ())?))
+                    }
+                    ("re.loop", [lo, hi]) if !params.is_empty() => {
+                        let a = (&params[0]).try_into()?;
+                        let lo = lo.parse().map_err(|_| ())?;
+                        Box::new(RegFun::Loop(a, lo, hi.parse().map_err(|_| ())?))
+                    }
+                    _ => Err(())?,
+                }))
             }
+            Term::Literal(_) => Err(()),
         }
     }
+}
 
-    fn call(f: &str, args: Vec<Literal>) -> Option<Literal> {
-        match (f, args.as_slice()) {
-            // Core
-            ("not", [Literal::Bool(b)]) => Some((!*b).into()),
-
-            ("=>", [Literal::Bool(x), Literal::Bool(y)]) => Some((!*x || *y).into()),
-
-            ("and", [Literal::Bool(x), Literal::Bool(y)]) => Some((*x && *y).into()),
-
-            ("or", [Literal::Bool(x), Literal::Bool(y)]) => Some((*x || *y).into()),
-
-            ("xor", [Literal::Bool(x), Literal::Bool(y)]) => Some((*x ^ *y).into()),
-
-            ("=", [Literal::Bool(x), Literal::Bool(y)]) => Some((*x == *y).into()),
-            ("=", [Literal::Numeral(x), Literal::Numeral(y)]) => Some((*x == *y).into()),
-            ("=", [Literal::String(x), Literal::String(y)]) => Some((*x == *y).into()),
-
-            ("distinct", [Literal::Bool(x), Literal::Bool(y)]) => Some((*x != *y).into()),
-            ("distinct", [Literal::Numeral(x), Literal::Numeral(y)]) => Some((*x != *y).into()),
-            ("distinct", [Literal::String(x), Literal::String(y)]) => Some((*x != *y).into()),
+impl From<&Literal> for Expr {
+    fn from(value: &Literal) -> Self {
+        match value {
+            Literal::Numeral(n) => Expr::ConstInt(*n),
+            Literal::Bool(b) => Expr::ConstBool(*b),
+            Literal::String(s) => Expr::ConstStr(s.to_owned()),
+        }
+    }
+}
 
-            ("ite", [Literal::Bool(c), t @ Literal::Bool(_), e @ Literal::Bool(_)]) => {
-                Some(if *c { t } else { e }.clone())
-            }
-            ("ite", [Literal::Bool(c), t @ Literal::Numeral(_), e @ Literal::Numeral(_)]) => {
-                Some(if *c { t } else { e }.clone())
-            }
-            ("ite", [Literal::Bool(c), t @ Literal::String(_), e @ Literal::String(_)]) => {
-                Some(if *c { t } else { e }.clone())
-            }
+impl TryFrom<Expr> for Literal {
+    type Error = ();
 
-            // Ints
-            ("-", [Literal::Numeral(n)]) => Some((-*n).into()),
+    fn try_from(value: Expr) -> Result<Self, Self::Error> {
+        match value {
+            Expr::ConstInt(n) => Ok(Literal::Numeral(n)),
+            Expr::ConstBool(b) => Ok(Literal::Bool(b)),
+            Expr::ConstStr(s) => Ok(Literal::String(s)),
+            _ => Err(()),
+        }
+    }
+}
 
-            ("-", [Literal::Numeral(x), Literal::Numeral(y)]) => Some((*x - *y).into()),
+impl Term {
+    /// Evaluates a term by lowering it to an [`Expr`], so that constraints and
+    /// candidate programs are judged by the same interpreter.
+    pub fn eval(&self, env: &HashMap<String, Literal>) -> Option<Literal> {
+        let expr: Expr = self.try_into().ok()?;
+        let env = env.iter().map(|(x, v)| (x.to_owned(), v.into())).collect();
+        expr.eval(&env).ok()?.try_into().ok()
+    }
+}
 
-            ("+", [Literal::Numeral(x), Literal::Numeral(y)]) => Some((*x + *y).into()),
+/// The naming convention used when printing programs back to SMT-LIB.
+/// Both conventions are accepted when parsing.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Dialect {
+    /// SMT-LIB 2.6 names, e.g. `str.to_int` and `str.from_int`
+    #[default]
+    Standard,
+    /// Pre-2.6 names, e.g. `str.to.int` and `int.to.str`
+    Legacy,
+}
 
-            ("*", [Literal::Numeral(x), Literal::Numeral(y)]) => Some((*x * *y).into()),
+impl Dialect {
+    fn name(self, standard: &'static str, legacy: &'static str) -> &'static str {
+        match self {
+            Dialect::Standard => standard,
+            Dialect::Legacy => legacy,
+        }
+    }
+}
 
-            ("div", [Literal::Numeral(x), Literal::Numeral(y)]) if *y != 0 => {
-                Some((*x / *y).into())
+impl Expr {
+    /// Converts a program back into an SMT-LIB term using the names of `dialect`.
+    pub fn to_term(&self, dialect: Dialect) -> Term {
+        match self {
+            Expr::ConstStr(s) => Term::Literal(Literal::String(s.to_owned())),
+            // numerals are non-negative in SMT-LIB
+            Expr::ConstInt(n) if *n < 0 => {
+                Term::Application("-".to_string(), vec![Term::Literal(Literal::Numeral(-n))])
             }
-
-            ("mod", [Literal::Numeral(x), Literal::Numeral(y)]) => Some((*x % *y).into()),
-
-            ("abs", [Literal::Numeral(n)]) => Some((*n).abs().into()),
-
-            ("<=", [Literal::Numeral(x), Literal::Numeral(y)]) => Some((*x <= *y).into()),
-
-            ("<", [Literal::Numeral(x), Literal::Numeral(y)]) => Some((*x < *y).into()),
-
-            (">=", [Literal::Numeral(x), Literal::Numeral(y)]) => Some((*x >= *y).into()),
-
-            (">", [Literal::Numeral(x), Literal::Numeral(y)]) => Some((*x > *y).into()),
-
-            // String functions
-            ("str.++", [Literal::String(a), Literal::String(b)]) => Some((a.to_owned() + b).into()),
-
-            ("str.len", [Literal::String(s)]) => Some((s.len() as i64).into()),
-
-            ("str.<", [Literal::String(a), Literal::String(b)]) => Some((a < b).into()),
-
-            // String additional functions
-            ("str.<=", [Literal::String(a), Literal::String(b)]) => Some((a <= b).into()),
-
-            ("str.at", [Literal::String(s), Literal::Numeral(n)]) => Some(
-                s.as_bytes()
-                    .get(*n as usize)
-                    .map_or("".into(), |c| c.to_string())
-                    .into(),
+            Expr::ConstInt(n) => Term::Literal(Literal::Numeral(*n)),
+            Expr::ConstBool(b) => Term::Literal(Literal::Bool(*b)),
+            Expr::Var(name) => Term::Identifier(name.to_owned()),
+            Expr::If(c, t, e) => Term::Application(
+                "ite".to_string(),
+                vec![c.to_term(dialect), t.to_term(dialect), e.to_term(dialect)],
             ),
-
-            (
-                "str.substr",
-                [Literal::String(s), Literal::Numeral(start), Literal::Numeral(end)],
-            ) => Some(
-                s.substring(*start as usize, *end as usize)
-                    .to_string()
-                    .into(),
-            ),
-
-            ("str.prefixof", [Literal::String(s), Literal::String(t)]) => {
-                Some(s.starts_with(t).into())
-            }
-
-            ("str.suffixof", [Literal::String(s), Literal::String(t)]) => {
-                Some(s.ends_with(t).into())
-            }
-
-            ("str.contains", [Literal::String(s), Literal::String(t)]) => {
-                Some(s.contains(t).into())
+            Expr::Call(f) => {
+                let app = |name: &str, args: &[&Expr]| {
+                    let args = args.iter().map(|e| e.to_term(dialect)).collect();
+                    Term::Application(name.to_string(), args)
+                };
+                match f.as_ref() {
+                    Func::Append(a, b) => app("str.++", &[a, b]),
+                    Func::StrLen(a) => app("str.len", &[a]),
+                    Func::StrAt(a, b) => app("str.at", &[a, b]),
+                    Func::SubStr(a, b, c) => app("str.substr", &[a, b, c]),
+                    Func::IsPre(a, b) => app("str.prefixof", &[a, b]),
+                    Func::IsPost(a, b) => app("str.suffixof", &[a, b]),
+                    Func::Contains(a, b) => app("str.contains", &[a, b]),
+                    Func::Index(a, b, c) => app("str.indexof", &[a, b, c]),
+                    Func::Replace(a, b, c) => app("str.replace", &[a, b, c]),
+                    Func::ReplaceAll(a, b, c) => app(
+                        dialect.name("str.replace_all", "str.replaceall"),
+                        &[a, b, c],
+                    ),
+                    Func::ReplaceRe(a, r, c) => Term::Application(
+                        "str.replace_re".to_string(),
+                        vec![a.to_term(dialect), r.to_term(dialect), c.to_term(dialect)],
+                    ),
+                    Func::ReplaceReAll(a, r, c) => Term::Application(
+                        "str.replace_re_all".to_string(),
+                        vec![a.to_term(dialect), r.to_term(dialect), c.to_term(dialect)],
+                    ),
+                    Func::InRe(a, r) => Term::Application(
+                        dialect.name("str.in_re", "str.in.re").to_string(),
+                        vec![a.to_term(dialect), r.to_term(dialect)],
+                    ),
+                    Func::IsDigit(a) => app("str.is_digit", &[a]),
+                    Func::ToCode(a) => app("str.to_code", &[a]),
+                    Func::FromCode(a) => app("str.from_code", &[a]),
+                    Func::Leq(a, b) => app("<=", &[a, b]),
+                    Func::Geq(a, b) => app(">=", &[a, b]),
+                    Func::Lt(a, b) => app("<", &[a, b]),
+                    Func::Gt(a, b) => app(">", &[a, b]),
+                    Func::Eql(a, b) => app("=", &[a, b]),
+                    Func::Add(a, b) => app("+", &[a, b]),
+                    Func::Min(a, b) => app("-", &[a, b]),
+                    Func::Mult(a, b) => app("*", &[a, b]),
+                    Func::Div(a, b) => app("div", &[a, b]),
+                    Func::Abs(a) => app("abs", &[a]),
+                    Func::Mod(a, b) => app("mod", &[a, b]),
+                    Func::NegI(a) => app("-", &[a]),
+                    Func::NegB(a) => app("not", &[a]),
+                    Func::And(a, b) => app("and", &[a, b]),
+                    Func::Or(a, b) => app("or", &[a, b]),
+                    Func::Xor(a, b) => app("xor", &[a, b]),
+                    Func::Implies(a, b) => app("=>", &[a, b]),
+                    Func::LexEq(a, b) => app("=", &[a, b]),
+                    Func::LexLt(a, b) => app("str.<", &[a, b]),
+                    Func::LexLeq(a, b) => app("str.<=", &[a, b]),
+                    // there is no `str.>=`, so swap the arguments of `str.<=`
+                    Func::LexGeq(a, b) => app("str.<=", &[b, a]),
+                    Func::StrToInt(a) => app(dialect.name("str.to_int", "str.to.int"), &[a]),
+                    Func::IntToStr(a) => app(dialect.name("str.from_int", "int.to.str"), &[a]),
+                }
             }
+        }
+    }
+}
 
-            ("str.indexof", [Literal::String(s), Literal::String(t), Literal::Numeral(i)])
-                if *i >= 0 =>
-            {
-                Some(
-                    (s.chars()
-                        .skip(*i as usize)
-                        .collect::<String>()
-                        .find(t)
-                        .map_or(-1, |i| i as i64))
-                    .into(),
-                )
+impl RegLang {
+    /// Converts a regular expression back into an SMT-LIB term using the names of `dialect`.
+    pub fn to_term(&self, dialect: Dialect) -> Term {
+        match self {
+            RegLang::Nil => Term::Identifier(dialect.name("re.none", "re.nostr").to_string()),
+            RegLang::All => Term::Identifier("re.all".to_string()),
+            RegLang::AllChar => Term::Identifier("re.allchar".to_string()),
+            RegLang::Var(name) => Term::Identifier(name.to_owned()),
+            RegLang::RCall(f) => {
+                let app = |name: &str, args: Vec<Term>| Term::Application(name.to_string(), args);
+                match f.as_ref() {
+                    RegFun::Sequence(a, b) => {
+                        app("re.++", vec![a.to_term(dialect), b.to_term(dialect)])
+                    }
+                    RegFun::Union(a, b) => {
+                        app("re.union", vec![a.to_term(dialect), b.to_term(dialect)])
+                    }
+                    RegFun::Inter(a, b) => {
+                        app("re.inter", vec![a.to_term(dialect), b.to_term(dialect)])
+                    }
+                    RegFun::Star(a) => app("re.*", vec![a.to_term(dialect)]),
+                    RegFun::One(a) => app("re.+", vec![a.to_term(dialect)]),
+                    RegFun::Opt(a) => app("re.opt", vec![a.to_term(dialect)]),
+                    RegFun::Range(a, b) => {
+                        app("re.range", vec![a.to_term(dialect), b.to_term(dialect)])
+                    }
+                    RegFun::FromStr(a) => app(
+                        dialect.name("str.to_re", "str.to.re"),
+                        vec![a.to_term(dialect)],
+                    ),
+                    RegFun::Comp(a) => app("re.comp", vec![a.to_term(dialect)]),
+                    RegFun::Diff(a, b) => {
+                        app("re.diff", vec![a.to_term(dialect), b.to_term(dialect)])
+                    }
+                    RegFun::Power(a, n) => app(
+                        &indexed("re.^", &[&n.to_string()]),
+                        vec![a.to_term(dialect)],
+                    ),
+                    RegFun::Loop(a, lo, hi) => app(
+                        &indexed("re.loop", &[&lo.to_string(), &hi.to_string()]),
+                        vec![a.to_term(dialect)],
+                    ),
+                }
             }
+        }
+    }
+}
 
-            ("str.replace", [Literal::String(s), Literal::String(t), Literal::String(u)]) => {
-                Some(s.replacen(t, u, 1).into())
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::Translate;
+
+    #[test]
+    fn ground_constraints_hold() {
+        let conjecture = parse_file(
+            r#"
+            (constraint (= (str.at "αβγ" 1) "β"))
+            (constraint (= (str.indexof "abcabc" "c" 3) 5))
+            (constraint (str.prefixof "ab" "abc"))
+            (constraint (not (str.suffixof "ab" "abc")))
+            (constraint (= (str.substr "abcdef" 1 3) "bcd"))
+            (constraint (=> (< 1 2) (distinct "a" "b")))
+            (constraint (= (ite (> 1 2) 0 1) 1))
+            (constraint (xor (str.< "ab" "b") (str.is_digit "a")))
+            (constraint (= (str.from_code (str.to_code "z")) "z"))
+            (constraint (= (str.to_int "42") (str.to.int "42")))
+            (constraint (= (str.from_int 42) (int.to.str 42)))
+            (constraint (= (str.replace_all "abab" "b" "c") (str.replaceall "abab" "b" "c")))
+            (constraint (str.in_re "a1" (re.++ re.allchar (re.range "0" "9"))))
+            (constraint (not (str.in_re "ab" (re.range "a" "bc"))))
+            (constraint (str.in_re "aaaa" (re.inter (re.* (str.to_re "aa")) (re.comp (str.to_re "aa")))))
+            (constraint (str.in_re "abab" ((_ re.loop 1 2) (str.to_re "ab"))))
+            (constraint (str.in_re "aaa" (( _  re.^ 3 ) (str.to_re "a"))))
+            (constraint (not (str.in_re "ab" (re.diff re.all (str.to_re "ab")))))
+            (constraint (= (str.replace_re "xabbb" (re.+ (str.to_re "b")) "c") "xacbb"))
+            (constraint (= (str.replace_re_all "a.b.c" (str.to_re ".") "") "abc"))
+            (constraint (= (str.++ "a" "b" "c") "abc"))
+            (constraint (= (- 10 3 2) (+ 1 2 2)))
+            (constraint (= (* 2 3 4) 24))
+            (constraint (not (and true true false)))
+            (constraint (or false false true))
+            "#,
+        )
+        .expect("parse");
+        for constraint in &conjecture.constraints {
+            assert_eq!(
+                constraint.eval(&HashMap::new()),
+                Some(Literal::Bool(true)),
+                "{}",
+                constraint
+            );
+        }
+    }
 
-            ("str.replace_all", [Literal::String(s), Literal::String(t), Literal::String(u)]) => {
-                Some(s.replace(t, u).into())
-            }
+    #[test]
+    fn print_in_dialect() {
+        let program = Expr::call(Func::IntToStr(Expr::call(Func::StrToInt(Expr::Var(
+            "name".into(),
+        )))));
+        assert_eq!(
+            program.to_term(Dialect::Standard).to_string(),
+            "(str.from_int (str.to_int name))"
+        );
+        assert_eq!(
+            program.to_term(Dialect::Legacy).to_string(),
+            "(int.to.str (str.to.int name))"
+        );
+    }
 
-            _ => None,
+    #[test]
+    fn reglan_grammar() {
+        let conjecture = parse_file(include_str!("../assets/logics/S.smt2")).expect("parse");
+        let grammar = &conjecture.functions_to_synthesize[0].grammar;
+        let (_, _, y_rl) = &grammar.rules[1];
+        assert!(matches!(&y_rl[1], GTerm::BFTerm(BFTerm::Identifier(x)) if x == "xr"));
+
+        let bank = crate::enumerate::bottom_up(grammar, 2);
+        assert!(bank["RegLan"].iter().any(|t| t.to_string() == "(re.* xr)"));
+        for term in &bank["RegLan"] {
+            let regex = RegLang::try_from(term).expect("regex");
+            assert_eq!(
+                regex.to_term(Dialect::Standard).to_string(),
+                term.to_string()
+            );
         }
+        // the `RegLan` parameter `xr` has no value in an `Expr` environment
+        let xr = RegLang::try_from(&Term::Identifier("xr".into())).unwrap();
+        assert!(xr.translate(&HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn weighted_grammar() {
+        let file = include_str!("../assets/benchmarks/bikes-weighted.sl");
+        let mut conjecture = parse_file(file).expect("parse");
+        assert_eq!(conjecture.weights, vec![("w".to_string(), 1)]);
+        let grammar = &mut conjecture.functions_to_synthesize[0].grammar;
+        assert_eq!(grammar.weights["ntString"], vec![1, 1, 1, 4, 1, 3, 2]);
+        assert_eq!(grammar.op_weights().op(Op::SubStr), 2);
+
+        let solutions = parse_solutions(
+            "(define-fun f ((name String)) String (str.substr name 0 (- (str.len name) 3)))",
+        )
+        .expect("solutions");
+        grammar.learn_weights(&solutions);
+        let ints = &grammar.weights["ntInt"];
+        // `-` and `str.len` were used, `+` was not
+        assert!(ints[7] < ints[6] && ints[8] < ints[6]);
     }
 }
@@ -0,0 +1,245 @@
+use crate::apply::Program;
+use crate::language::Expr;
+use crate::sygus::{self, Conjecture, Literal, Sort};
+use std::collections::HashSet;
+use std::io::{self, BufRead};
+
+/// The default grammar of string functions, that of the benchmarks, with
+/// every `String` parameter as a variable
+pub const STRING_GRAMMAR: &str = include_str!("../assets/grammars/strings.sl");
+
+/// A spreadsheet of text cells under a row of headers, read from CSV or TSV
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Table {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Reads `text` with fields separated by `delimiter`, as [`read_record`]
+    /// does. Short rows are padded with empty cells.
+    pub fn parse(text: &str, delimiter: char) -> Result<Table, String> {
+        let mut input = text.as_bytes();
+        let mut next = || read_record(&mut input, delimiter).map_err(|e| e.to_string());
+        let headers = next()?.ok_or("no header row")?;
+        let mut rows = Vec::new();
+        while let Some(mut row) = next()? {
+            if row.len() > headers.len() {
+                return Err(format!(
+                    "row {} has more cells than headers",
+                    rows.len() + 1
+                ));
+            }
+            row.resize(headers.len(), String::new());
+            rows.push(row);
+        }
+        Ok(Table { headers, rows })
+    }
+
+    /// The column called `header`
+    pub fn column(&self, header: &str) -> Option<usize> {
+        self.headers.iter().position(|h| h == header)
+    }
+
+    /// Writes the table back out, quoting the fields that need it
+    pub fn to_string(&self, delimiter: char) -> String {
+        std::iter::once(&self.headers)
+            .chain(&self.rows)
+            .map(|record| write_record(record, delimiter))
+            .collect()
+    }
+
+    /// The rows with an empty `output` cell
+    pub fn unlabeled(&self, output: usize) -> Vec<usize> {
+        (0..self.rows.len())
+            .filter(|&i| self.rows[i][output].is_empty())
+            .collect()
+    }
+
+    /// The parameter names of the `inputs` columns: their headers, made into
+    /// distinct SyGuS symbols
+    pub fn parameters(&self, inputs: &[usize]) -> Vec<String> {
+        let mut names = HashSet::new();
+        inputs
+            .iter()
+            .map(|&i| {
+                let mut name: String = self.headers[i]
+                    .chars()
+                    .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                    .collect();
+                if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                    name.insert(0, 'x');
+                }
+                let base = name.clone();
+                let mut n = 1;
+                while !names.insert(name.clone()) {
+                    n += 1;
+                    name = format!("{}_{}", base, n);
+                }
+                name
+            })
+            .collect()
+    }
+
+    /// A conjecture for a string function `f` of the `inputs` columns, with a
+    /// constraint per row whose `output` cell is filled in, over
+    /// [`STRING_GRAMMAR`]
+    pub fn conjecture(&self, inputs: &[usize], output: usize) -> Result<Conjecture, String> {
+        let parameters = self.parameters(inputs);
+        let string = |s: &String| Literal::String(s.clone()).to_string();
+        let sorted: Vec<String> = parameters
+            .iter()
+            .map(|p| format!("({} String)", p))
+            .collect();
+        let mut text = format!(
+            "(set-logic SLIA)\n(synth-fun f ({}) String\n{})\n",
+            sorted.join(" "),
+            STRING_GRAMMAR,
+        );
+        for p in &parameters {
+            text.push_str(&format!("(declare-var {} String)\n", p));
+        }
+        for row in self.rows.iter().filter(|row| !row[output].is_empty()) {
+            let args: Vec<String> = inputs.iter().map(|&i| string(&row[i])).collect();
+            text.push_str(&format!(
+                "(constraint (= (f {}) {}))\n",
+                args.join(" "),
+                string(&row[output])
+            ));
+        }
+        text.push_str("(check-synth)\n");
+        sygus::parse_file(&text).map_err(|e| e.to_string())
+    }
+
+    /// Fills the empty `output` cells with `program` applied to the `inputs`
+    /// cells of their row. Cells where the program fails stay empty.
+    pub fn fill(&mut self, inputs: &[usize], output: usize, program: &Expr) {
+        let string = || Sort::Identifier("String".to_string());
+        let parameters = self.parameters(inputs).into_iter().map(|p| (p, string()));
+        let program = Program::new(parameters.collect(), program.clone());
+        for i in self.unlabeled(output) {
+            let cells: Vec<&str> = inputs.iter().map(|&c| self.rows[i][c].as_str()).collect();
+            if let Ok(result) = program.run(&cells) {
+                self.rows[i][output] = result;
+            }
+        }
+    }
+}
+
+/// Reads the next record of `input`, with fields separated by `delimiter`,
+/// or `None` at the end. Fields may be quoted, with `""` for a quote inside,
+/// to hold delimiters and newlines, so a record may span several lines.
+pub fn read_record(input: &mut impl BufRead, delimiter: char) -> io::Result<Option<Vec<String>>> {
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let (mut record, mut field) = (Vec::new(), String::new());
+    let mut quoted = false;
+    loop {
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' if quoted => quoted = false,
+                '"' if field.is_empty() => quoted = true,
+                c if quoted => field.push(c),
+                c if c == delimiter => record.push(std::mem::take(&mut field)),
+                '\r' if chars.peek() == Some(&'\n') => {}
+                '\n' => {}
+                c => field.push(c),
+            }
+        }
+        if !quoted {
+            break;
+        }
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            let error = "unterminated quoted field";
+            return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+        }
+    }
+    record.push(field);
+    Ok(Some(record))
+}
+
+/// One line of fields separated by `delimiter`, quoting those that need it
+pub fn write_record(record: &[String], delimiter: char) -> String {
+    let fields: Vec<String> = record
+        .iter()
+        .map(|f| {
+            if f.contains([delimiter, '"', '\n', '\r']) {
+                format!("\"{}\"", f.replace('"', "\"\""))
+            } else {
+                f.clone()
+            }
+        })
+        .collect();
+    format!("{}\n", fields.join(&delimiter.to_string()))
+}
+
+/// `name`, or `name_2`, `name_3` and so on if `headers` already has it
+pub fn fresh(headers: &[String], name: &str) -> String {
+    let mut fresh = name.to_string();
+    let mut n = 1;
+    while headers.contains(&fresh) {
+        n += 1;
+        fresh = format!("{}_{}", name, n);
+    }
+    fresh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::Func;
+
+    #[test]
+    fn conjecture_from_csv() {
+        let text = "full name,first\n\"Jan, \"\"JK\"\" Kotas\",Jan\nBo Li,\n";
+        let mut table = Table::parse(text, ',').expect("csv");
+        assert_eq!(table.rows[0][0], "Jan, \"JK\" Kotas");
+        assert_eq!(table.to_string(','), text);
+        assert_eq!(table.parameters(&[0]), ["full_name"]);
+
+        let conjecture = table.conjecture(&[0], 1).expect("conjecture");
+        let function = &conjecture.functions_to_synthesize[0];
+        assert_eq!(function.parameters[0].0, "full_name");
+        // the grammar of the benchmarks, over the input column
+        let bikes = sygus::parse_file(include_str!("../assets/benchmarks/bikes.sl")).unwrap();
+        let productions = |f: &sygus::Function| -> Vec<usize> {
+            f.grammar
+                .rules
+                .iter()
+                .map(|(_, _, rhs)| rhs.len())
+                .collect()
+        };
+        assert_eq!(
+            productions(function),
+            productions(&bikes.functions_to_synthesize[0])
+        );
+        assert_eq!(
+            conjecture.specification(),
+            Some(vec![(
+                Expr::ConstStr("Jan, \"JK\" Kotas".into()),
+                Expr::ConstStr("Jan".into())
+            )])
+        );
+
+        let name = || Expr::Var("full_name".into());
+        let space = Expr::call(Func::Index(
+            name(),
+            Expr::ConstStr(" ".into()),
+            Expr::ConstInt(0),
+        ));
+        table.fill(
+            &[0],
+            1,
+            &Expr::call(Func::SubStr(name(), Expr::ConstInt(0), space)),
+        );
+        assert_eq!(table.rows[1][1], "Bo");
+    }
+}